@@ -0,0 +1,98 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::{hint::black_box, sync::Arc};
+use terrain_data::prelude::*;
+use tokio::runtime::Runtime;
+
+world! {
+    chunk_width: 16,
+    chunk_height: 16,
+    subchunk_depth: 16,
+    num_subchunks: 16,
+    Block r#as block: u8 = 4,
+    SkyLight r#as sky_light: u8 = 4,
+    Exposed r#as is_exposed: bool = 1,
+}
+
+const CODECS: [Compression; 4] = [
+    Compression::None,
+    Compression::Lz4,
+    Compression::Zstd(3),
+    Compression::Zstd(19),
+];
+
+/// Fills a chunk with rolling stone hills, a dirt and grass surface,
+/// a few ore pockets and full sky light above the ground.
+fn generate_terrain(world: &World, chunk_pos: ChunkPosition) {
+    world.add_chunk(chunk_pos, None).unwrap();
+
+    for pos in World::chunk_coords(chunk_pos) {
+        let height: i32 =
+            64 + ((pos.x as f32 * 0.4).sin() * 6.0 + (pos.y as f32 * 0.3).cos() * 4.0) as i32;
+
+        let block: u8 = match pos.z {
+            z if z > height => 0,
+            z if z == height => 3,
+            z if z > height - 4 => 2,
+            z if (pos.x * 7 + pos.y * 13 + z * 5) % 97 == 0 => 4,
+            _ => 1,
+        };
+
+        if block != 0 {
+            world.set_block(pos, block).unwrap();
+        } else {
+            world.set_sky_light(pos, 15).unwrap();
+        }
+
+        if pos.z == height {
+            world.set_is_exposed(pos, true).unwrap();
+        }
+    }
+}
+
+fn compression(c: &mut Criterion) {
+    let runtime: Runtime = Runtime::new().unwrap();
+    let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+
+    for codec in CODECS.into_iter().filter(Compression::is_supported) {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: World = World::builder()
+            .storage(Arc::clone(&storage))
+            .compression(codec)
+            .build();
+
+        generate_terrain(&world, chunk_pos);
+        runtime.block_on(world.save_chunk(chunk_pos)).unwrap();
+
+        let size: usize = runtime
+            .block_on(storage.read(chunk_pos))
+            .unwrap()
+            .unwrap()
+            .len();
+        println!("{codec:?}: {size} bytes per chunk");
+
+        let mut group = c.benchmark_group(format!("{codec:?}"));
+
+        group.bench_function("save", |b| {
+            b.iter(|| {
+                world
+                    .set_block(black_box(BlockPosition::new(0, 0, 255)), 1)
+                    .unwrap();
+                runtime.block_on(world.save_chunk(chunk_pos)).unwrap();
+            })
+        });
+
+        group.bench_function("load", |b| {
+            b.iter(|| {
+                runtime.block_on(world.unload_chunk(chunk_pos)).unwrap();
+                runtime
+                    .block_on(world.load_chunk(black_box(chunk_pos)))
+                    .unwrap();
+            })
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, compression);
+criterion_main!(benches);
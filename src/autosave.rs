@@ -0,0 +1,243 @@
+use crate::{
+    core::ChunkPosition,
+    error::{AccessError, ChunkAccessError, ChunkStoreError},
+    parallel::run_limited,
+    storage::BoxFuture,
+};
+use std::{panic, sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::{self, Instant, Interval, MissedTickBehavior},
+};
+
+/// Writes dirty chunks to storage on behalf of an [`Autosave`] service.
+/// Implemented by every world generated by `world!`.
+pub trait ChunkSaver: Send + Sync + 'static {
+    /// Returns the positions of all loaded chunks modified since they were last saved.
+    fn dirty_chunks(&self) -> Vec<ChunkPosition>;
+
+    /// Writes the chunk at the passed position to storage and keeps it loaded.
+    fn save(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<(), ChunkStoreError>>;
+}
+
+/// Outcome of a single flush of dirty chunks.
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    /// Chunks written to storage.
+    pub saved: Vec<ChunkPosition>,
+    /// Chunks that failed to save. They stay dirty and are retried on the next flush.
+    pub errors: Vec<(ChunkPosition, ChunkStoreError)>,
+}
+
+/// Writes every dirty chunk of the passed world, running at most `max_concurrent` saves at once.
+/// Chunks unloaded since they were listed were already persisted and are skipped.
+pub async fn flush<W: ChunkSaver>(world: &Arc<W>, max_concurrent: usize) -> FlushReport {
+    let mut report: FlushReport = FlushReport::default();
+
+    for (pos, result) in run_limited(
+        world,
+        max_concurrent.max(1),
+        world.dirty_chunks(),
+        |world, pos| async move { world.save(pos).await },
+    )
+    .await
+    {
+        match result {
+            Ok(()) => report.saved.push(pos),
+            Err(ChunkStoreError::Access(AccessError::ChunkAccess(
+                ChunkAccessError::ChunkUnloaded(_),
+            ))) => {}
+            Err(e) => report.errors.push((pos, e)),
+        }
+    }
+
+    report
+}
+
+/// Opt-in service periodically flushing the dirty chunks of a world on a tokio task.
+pub struct Autosave<W> {
+    world: Arc<W>,
+    interval: Duration,
+    max_concurrent: usize,
+    reports: Option<mpsc::UnboundedSender<FlushReport>>,
+}
+
+impl<W: ChunkSaver> Autosave<W> {
+    /// Creates a service flushing every 30 seconds, running up to 4 saves at once.
+    pub fn new(world: Arc<W>) -> Self {
+        Self {
+            world,
+            interval: Duration::from_secs(30),
+            max_concurrent: 4,
+            reports: None,
+        }
+    }
+
+    /// Sets the time between two flushes.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Sets how many chunks are written at once during a flush.
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self
+    }
+
+    /// Sets a channel receiving the report of every periodic flush.
+    /// The final flush on shutdown is returned by [`AutosaveHandle::stop`] instead.
+    pub fn reports(mut self, reports: mpsc::UnboundedSender<FlushReport>) -> Self {
+        self.reports = Some(reports);
+        self
+    }
+
+    /// Starts flushing on a tokio task. The first flush happens one interval from now.
+    pub fn spawn(self) -> AutosaveHandle {
+        let (stop, mut stopped) = oneshot::channel::<()>();
+
+        let task: JoinHandle<FlushReport> = tokio::spawn(async move {
+            let mut interval: Interval =
+                time::interval_at(Instant::now() + self.interval, self.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let report: FlushReport = flush(&self.world, self.max_concurrent).await;
+
+                        if let Some(reports) = &self.reports {
+                            // a dropped receiver only means nobody listens anymore
+                            let _ = reports.send(report);
+                        }
+                    }
+                    // stopped explicitly or the handle was dropped
+                    _ = &mut stopped => break,
+                }
+            }
+
+            flush(&self.world, self.max_concurrent).await
+        });
+
+        AutosaveHandle { stop, task }
+    }
+}
+
+/// Controls a running [`Autosave`] service.
+/// Dropping the handle stops the service after a final flush, without waiting for it.
+pub struct AutosaveHandle {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<FlushReport>,
+}
+
+impl AutosaveHandle {
+    /// Stops the service, waiting for any running flush and a final one that saves every remaining dirty chunk.
+    /// Returns the report of the final flush.
+    pub async fn stop(self) -> FlushReport {
+        let _ = self.stop.send(());
+
+        match self.task.await {
+            Ok(report) => report,
+            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+            // only happens while the runtime shuts down
+            Err(_) => FlushReport::default(),
+        }
+    }
+
+    /// Returns true if the service stopped.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, sync::Mutex};
+
+    /// Clears dirty chunks when saved, failing for one position and finding another unloaded.
+    struct RecordingSaver {
+        dirty: Mutex<HashSet<ChunkPosition>>,
+        failing: ChunkPosition,
+        unloaded: ChunkPosition,
+    }
+
+    impl ChunkSaver for RecordingSaver {
+        fn dirty_chunks(&self) -> Vec<ChunkPosition> {
+            let mut dirty: Vec<ChunkPosition> =
+                self.dirty.lock().unwrap().iter().copied().collect();
+            dirty.sort_by_key(|pos| (pos.x, pos.y));
+            dirty
+        }
+
+        fn save(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<(), ChunkStoreError>> {
+            Box::pin(async move {
+                if pos == self.failing {
+                    return Err(std::io::Error::other("disk full").into());
+                }
+                if pos == self.unloaded {
+                    return Err(AccessError::from(ChunkAccessError::ChunkUnloaded(pos)).into());
+                }
+                self.dirty.lock().unwrap().remove(&pos);
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_periodic_and_final_flush() {
+        let saver: Arc<RecordingSaver> = Arc::new(RecordingSaver {
+            dirty: Mutex::new(HashSet::from([
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(1, 0),
+            ])),
+            failing: ChunkPosition::new(2, 0),
+            unloaded: ChunkPosition::new(-1, 0),
+        });
+        let (sender, mut receiver) = mpsc::unbounded_channel::<FlushReport>();
+        let handle: AutosaveHandle = Autosave::new(Arc::clone(&saver))
+            .interval(Duration::from_secs(10))
+            .max_concurrent(1)
+            .reports(sender)
+            .spawn();
+
+        time::sleep(Duration::from_secs(5)).await;
+        assert!(receiver.try_recv().is_err());
+
+        saver.dirty.lock().unwrap().insert(ChunkPosition::new(2, 0));
+        let report: FlushReport = receiver.recv().await.unwrap();
+        assert_eq!(
+            report.saved,
+            vec![ChunkPosition::new(0, 0), ChunkPosition::new(1, 0)]
+        );
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, ChunkPosition::new(2, 0));
+
+        saver.dirty.lock().unwrap().insert(ChunkPosition::new(3, 0));
+        let report: FlushReport = handle.stop().await;
+        assert_eq!(report.saved, vec![ChunkPosition::new(3, 0)]);
+        assert_eq!(report.errors.len(), 1);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_flush_skips_only_unloaded_chunks() {
+        let saver: Arc<RecordingSaver> = Arc::new(RecordingSaver {
+            dirty: Mutex::new(HashSet::from([
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(1, 0),
+            ])),
+            failing: ChunkPosition::new(0, 0),
+            unloaded: ChunkPosition::new(1, 0),
+        });
+
+        let report: FlushReport = flush(&saver, 2).await;
+        assert!(report.saved.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert!(matches!(
+            report.errors[0],
+            (pos, ChunkStoreError::Io(_)) if pos == ChunkPosition::new(0, 0)
+        ));
+    }
+}
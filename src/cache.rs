@@ -0,0 +1,240 @@
+use crate::core::{ChunkPosition, MemoryBudget, PositionHasher};
+use dashmap::{DashMap, mapref::entry::Entry};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "async-io")]
+use tokio::sync::Notify;
+
+/// Access time and estimated size of a loaded chunk, used to pick chunks to evict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedChunk {
+    pub pos: ChunkPosition,
+    pub last_access: u64,
+    pub size: usize,
+}
+
+/// Tracks the access order and pins of a world's loaded chunks
+/// and decides which of them to evict to stay within the memory budget.
+#[derive(Default)]
+pub struct ChunkCache {
+    budget: MemoryBudget,
+    clock: AtomicU64,
+    /// Estimated bytes of the loaded chunks, only tracked under a byte budget.
+    bytes: AtomicUsize,
+    /// Pin counts, so independent callers can pin the same chunk.
+    pinned: DashMap<ChunkPosition, usize, PositionHasher>,
+    #[cfg(feature = "async-io")]
+    over_budget: Notify,
+}
+
+impl ChunkCache {
+    pub fn new(budget: MemoryBudget) -> Self {
+        Self {
+            budget,
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn budget(&self) -> MemoryBudget {
+        self.budget
+    }
+
+    /// Returns true if chunks may be evicted, so accesses need to be tracked.
+    #[inline]
+    pub fn is_limited(&self) -> bool {
+        self.budget != MemoryBudget::Unlimited
+    }
+
+    /// Returns true if the loaded bytes are tracked, which only a byte budget needs.
+    #[inline]
+    pub fn tracks_bytes(&self) -> bool {
+        matches!(self.budget, MemoryBudget::Bytes(_))
+    }
+
+    /// Records that a loaded chunk changed from `old` to `new` estimated bytes.
+    #[inline]
+    pub fn resize(&self, old: usize, new: usize) {
+        if new > old {
+            self.bytes.fetch_add(new - old, Ordering::Relaxed);
+        } else {
+            self.bytes.fetch_sub(old - new, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns true if the tracked bytes exceed a byte budget.
+    #[inline]
+    pub fn is_over_bytes(&self) -> bool {
+        match self.budget {
+            MemoryBudget::Bytes(max) => self.bytes.load(Ordering::Relaxed) > max,
+            _ => false,
+        }
+    }
+
+    /// Returns true if `count` loaded chunks or the tracked bytes exceed the budget.
+    #[inline]
+    pub fn is_over_budget(&self, count: usize) -> bool {
+        match self.budget {
+            MemoryBudget::Chunks(max) => count > max,
+            _ => self.is_over_bytes(),
+        }
+    }
+
+    /// Returns a new access time, later than every one returned before.
+    #[inline]
+    pub fn touch(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Keeps the passed chunk from being evicted until it is unpinned as often as it was pinned.
+    pub fn pin(&self, pos: ChunkPosition) {
+        *self.pinned.entry(pos).or_insert(0) += 1;
+    }
+
+    /// Releases one pin of the passed chunk. Returns false if it was not pinned.
+    pub fn unpin(&self, pos: ChunkPosition) -> bool {
+        match self.pinned.entry(pos) {
+            Entry::Vacant(_) => false,
+            Entry::Occupied(mut entry) => {
+                *entry.get_mut() -= 1;
+                if *entry.get() == 0 {
+                    entry.remove();
+                }
+                true
+            }
+        }
+    }
+
+    /// Pins the passed chunk until the returned guard is dropped.
+    pub fn pin_guard(&self, pos: ChunkPosition) -> PinGuard<'_> {
+        self.pin(pos);
+        PinGuard { cache: self, pos }
+    }
+
+    #[inline]
+    pub fn is_pinned(&self, pos: ChunkPosition) -> bool {
+        self.pinned.contains_key(&pos)
+    }
+
+    /// Returns the unpinned chunks to evict, least recently used first,
+    /// so the remaining ones fit the budget. Pinned chunks can keep the world over budget.
+    pub fn victims(&self, mut chunks: Vec<CachedChunk>) -> Vec<ChunkPosition> {
+        let mut count: usize = chunks.len();
+        let mut bytes: usize = chunks.iter().map(|chunk| chunk.size).sum();
+
+        chunks.retain(|chunk| !self.is_pinned(chunk.pos));
+        chunks.sort_unstable_by_key(|chunk| chunk.last_access);
+
+        chunks
+            .into_iter()
+            .take_while(|chunk| {
+                let over: bool = match self.budget {
+                    MemoryBudget::Unlimited => false,
+                    MemoryBudget::Chunks(max) => count > max,
+                    MemoryBudget::Bytes(max) => bytes > max,
+                };
+                count -= 1;
+                bytes -= chunk.size;
+                over
+            })
+            .map(|chunk| chunk.pos)
+            .collect()
+    }
+
+    /// Wakes the background evictor. Does nothing without the `async-io` feature.
+    pub fn notify(&self) {
+        #[cfg(feature = "async-io")]
+        self.over_budget.notify_one();
+    }
+
+    /// Waits until [`Self::notify`] is called, returning at once if it was called since the last wait.
+    #[cfg(feature = "async-io")]
+    pub async fn notified(&self) {
+        self.over_budget.notified().await;
+    }
+}
+
+/// Releases one pin of its chunk when dropped,
+/// including when the owning future is cancelled.
+pub struct PinGuard<'a> {
+    cache: &'a ChunkCache,
+    pos: ChunkPosition,
+}
+
+impl Drop for PinGuard<'_> {
+    fn drop(&mut self) {
+        self.cache.unpin(self.pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(x: i32, last_access: u64, size: usize) -> CachedChunk {
+        CachedChunk {
+            pos: ChunkPosition::new(x, 0),
+            last_access,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_victims_least_recently_used_first() {
+        let chunks: Vec<CachedChunk> = vec![
+            cached(0, 4, 10),
+            cached(1, 1, 10),
+            cached(2, 3, 30),
+            cached(3, 2, 10),
+        ];
+
+        let cache: ChunkCache = ChunkCache::new(MemoryBudget::Chunks(2));
+        assert_eq!(
+            cache.victims(chunks.clone()),
+            vec![ChunkPosition::new(1, 0), ChunkPosition::new(3, 0)]
+        );
+
+        cache.pin(ChunkPosition::new(1, 0));
+        cache.pin(ChunkPosition::new(1, 0));
+        assert!(cache.unpin(ChunkPosition::new(1, 0)));
+        assert_eq!(
+            cache.victims(chunks.clone()),
+            vec![ChunkPosition::new(3, 0), ChunkPosition::new(2, 0)]
+        );
+
+        assert!(cache.unpin(ChunkPosition::new(1, 0)));
+        assert!(!cache.unpin(ChunkPosition::new(1, 0)));
+
+        let cache: ChunkCache = ChunkCache::new(MemoryBudget::Bytes(35));
+        assert_eq!(
+            cache.victims(chunks.clone()),
+            vec![
+                ChunkPosition::new(1, 0),
+                ChunkPosition::new(3, 0),
+                ChunkPosition::new(2, 0)
+            ]
+        );
+
+        let cache: ChunkCache = ChunkCache::new(MemoryBudget::Unlimited);
+        assert!(cache.victims(chunks).is_empty());
+    }
+
+    #[test]
+    fn test_tracked_bytes() {
+        let cache: ChunkCache = ChunkCache::new(MemoryBudget::Bytes(100));
+        assert!(cache.tracks_bytes());
+
+        cache.resize(0, 60);
+        cache.resize(0, 30);
+        assert!(!cache.is_over_budget(2));
+
+        cache.resize(30, 50);
+        assert!(cache.is_over_budget(2));
+
+        cache.resize(60, 0);
+        assert!(!cache.is_over_bytes());
+
+        let cache: ChunkCache = ChunkCache::new(MemoryBudget::Chunks(1));
+        assert!(!cache.tracks_bytes());
+        assert!(cache.is_over_budget(2));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::error::CompressionError;
+use serde::{Deserialize, Serialize};
+
+/// Codec applied to encoded chunk bodies. Recorded in each chunk header,
+/// so a world can switch codecs while older chunks stay readable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Fast block compression, requires the `lz4` feature.
+    Lz4,
+    /// Smaller output at a higher cost, requires the `zstd` feature.
+    /// Holds the compression level used when writing.
+    Zstd(i32),
+}
+
+impl Compression {
+    /// Returns true if this build can encode and decode the codec.
+    pub const fn is_supported(&self) -> bool {
+        match self {
+            Self::None => true,
+            Self::Lz4 => cfg!(feature = "lz4"),
+            Self::Zstd(_) => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Compresses the passed data.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match *self {
+            Self::None => Ok(data.to_vec()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(level) => Ok(zstd::bulk::compress(data, level)?),
+            #[allow(unreachable_patterns)]
+            other => Err(CompressionError::Unsupported(other)),
+        }
+    }
+
+    /// Restores data produced by [`Compression::compress`] with the same codec.
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match *self {
+            Self::None => Ok(data.to_vec()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into()),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(_) => Ok(zstd::stream::decode_all(data)?),
+            #[allow(unreachable_patterns)]
+            other => Err(CompressionError::Unsupported(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_supported_codecs() -> Result<(), CompressionError> {
+        let data: Vec<u8> = (0..4096).map(|i| (i / 512) as u8).collect();
+
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd(3)] {
+            if !compression.is_supported() {
+                assert!(matches!(
+                    compression.compress(&data),
+                    Err(CompressionError::Unsupported(c)) if c == compression
+                ));
+                continue;
+            }
+
+            let compressed: Vec<u8> = compression.compress(&data)?;
+            assert_eq!(compression.decompress(&compressed)?, data);
+
+            if compression != Compression::None {
+                assert!(compressed.len() < data.len());
+            }
+        }
+
+        Ok(())
+    }
+}
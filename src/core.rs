@@ -1,4 +1,14 @@
 use glam::{IVec2, IVec3};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::hash::BuildHasherDefault;
+
+/// Hasher of the maps keyed by chunk position.
+/// aHash with the `ahash` feature, the standard library's SipHash otherwise.
+#[cfg(feature = "ahash")]
+pub type PositionHasher = BuildHasherDefault<ahash::AHasher>;
+#[cfg(not(feature = "ahash"))]
+pub type PositionHasher = BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
 
 /// Stores the three dimensional integer position of a block.
 pub type BlockPosition = IVec3;
@@ -6,8 +16,12 @@ pub type BlockPosition = IVec3;
 /// Stores the two dimensional integer position of a chunk.
 pub type ChunkPosition = IVec2;
 
+/// Default directory a world saves to when no root is given.
 pub const CHUNKS_DIR: &str = "chunks";
 
+/// Name of the metadata file stored alongside a world's chunk files.
+pub const METADATA_FILE: &str = "world.bin";
+
 pub const CHUNK_ADJ_OFFSETS: [ChunkPosition; 4] = [
     ChunkPosition::new(-1, 0),
     ChunkPosition::new(1, 0),
@@ -24,15 +38,163 @@ pub const BLOCK_OFFSETS: [BlockPosition; 6] = [
     BlockPosition::new(0, 0, -1),
 ];
 
+/// On-disk layout used to persist chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageFormat {
+    /// One file per chunk.
+    #[default]
+    ChunkFiles,
+    /// Many chunks grouped into region files, see [`crate::region`].
+    Regions,
+}
+
+/// Lifecycle of a chunk position within a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkState {
+    /// Not in memory.
+    Unloaded,
+    /// Being read from storage.
+    Loading,
+    /// In memory and accessible.
+    Loaded,
+    /// Being written to storage, by a save or an unload.
+    Saving,
+    /// Running a generation stage.
+    Generating,
+}
+
+/// What loading does when a stored chunk fails its checksum or cannot be decoded.
+/// Data that is no chunk file or uses another format version is never treated as corrupted,
+/// loading it fails with [`crate::error::ChunkStoreError::InvalidHeader`] instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CorruptionPolicy {
+    /// Returns [`crate::error::ChunkStoreError::Corrupted`] and leaves the stored data alone.
+    #[default]
+    Fail,
+    /// Loads a chunk from the world's generator in its place, or an empty chunk without one.
+    /// It overwrites the damaged data once saved.
+    Regenerate,
+    /// Moves the damaged data aside with [`crate::storage::ChunkStorage::quarantine_blocking`],
+    /// then returns [`crate::error::ChunkStoreError::Corrupted`].
+    Quarantine,
+}
+
+/// How much a world keeps in memory before evicting the least recently used chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryBudget {
+    /// Chunks stay loaded until they are unloaded explicitly.
+    #[default]
+    Unlimited,
+    /// At most this many chunks stay loaded.
+    Chunks(usize),
+    /// Loaded chunks use at most about this many bytes.
+    Bytes(usize),
+}
+
+/// Name, bit width and raw default value of a single field declared in `world!`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FieldInfo {
+    pub name: String,
+    pub bits: u8,
+    pub default: u64,
+}
+
+/// Describes the layout of a world definition.
+/// Saved next to the chunk files so a save directory can be identified later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WorldMetadata {
+    pub chunk_width: u32,
+    pub chunk_height: u32,
+    pub chunk_depth: u32,
+    pub min_z: i32,
+    pub schema_version: u32,
+    pub fields: Vec<FieldInfo>,
+}
+
+/// A value that can be stored in a world field, packed into the field's bits.
+/// Signed integers are zig-zag encoded, so range errors report the encoded value.
 pub trait FieldType: Sized {
-    fn from_u64(v: u64) -> Self;
+    /// Number of bits needed to hold every value of the type.
+    /// `world!` rejects fields that declare more bits than this at compile time.
+    const BITS: u8;
+
+    /// Lets `world!` encode `#[default]` values at compile time, where `to_u64` cannot run.
+    /// True for types whose `to_u64` zig-zag encodes `self as i128`, false for plain `self as u64`.
+    #[doc(hidden)]
+    const ZIG_ZAG: bool = false;
+
+    /// Returns `None` if the raw value does not stand for any value of the type,
+    /// which field getters report as [`AccessError::InvalidValue`](crate::error::AccessError::InvalidValue).
+    fn from_u64(v: u64) -> Option<Self>;
     fn to_u64(self) -> u64;
 }
 
-impl FieldType for u8 {
+#[cfg(feature = "derive")]
+pub use terrain_data_derive::FieldType;
+
+/// Encodes a `#[default]` value cast to `i128` the way `FieldType::to_u64` would, in const context.
+#[doc(hidden)]
+pub const fn raw_default(value: i128, zig_zag: bool) -> u64 {
+    if zig_zag {
+        ((value << 1) ^ (value >> 127)) as u64
+    } else {
+        value as u64
+    }
+}
+
+macro_rules! impl_unsigned_field_type {
+    ($($ty:ty),*) => {
+        $(
+            impl FieldType for $ty {
+                const BITS: u8 = <$ty>::BITS as u8;
+
+                #[inline(always)]
+                fn from_u64(v: u64) -> Option<Self> {
+                    Some(v as Self)
+                }
+                #[inline(always)]
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+/// Signed types are zig-zag encoded, so values of small magnitude need few bits
+/// no matter their sign and zero stays the empty value.
+macro_rules! impl_signed_field_type {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl FieldType for $ty {
+                const BITS: u8 = <$ty>::BITS as u8;
+                const ZIG_ZAG: bool = true;
+
+                #[inline(always)]
+                fn from_u64(v: u64) -> Option<Self> {
+                    let v: $unsigned = v as $unsigned;
+                    Some((v >> 1) as Self ^ -((v & 1) as Self))
+                }
+                #[inline(always)]
+                fn to_u64(self) -> u64 {
+                    ((self << 1) ^ (self >> (<$ty>::BITS - 1))) as $unsigned as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned_field_type!(u8, u16, u32, u64, usize);
+impl_signed_field_type!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+
+impl FieldType for bool {
+    const BITS: u8 = 1;
+
     #[inline(always)]
-    fn from_u64(v: u64) -> Self {
-        v as Self
+    fn from_u64(v: u64) -> Option<Self> {
+        Some(v != 0)
     }
     #[inline(always)]
     fn to_u64(self) -> u64 {
@@ -40,13 +202,48 @@ impl FieldType for u8 {
     }
 }
 
-impl FieldType for bool {
+impl FieldType for char {
+    const BITS: u8 = 21;
+
+    /// Surrogates and values past `char::MAX` are not decoded.
     #[inline(always)]
-    fn from_u64(v: u64) -> Self {
-        v != 0
+    fn from_u64(v: u64) -> Option<Self> {
+        u32::try_from(v).ok().and_then(char::from_u32)
     }
     #[inline(always)]
     fn to_u64(self) -> u64 {
         self as u64
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: FieldType + Copy>(value: T) -> T {
+        T::from_u64(value.to_u64()).unwrap()
+    }
+
+    #[test]
+    fn test_field_types_round_trip() {
+        assert_eq!(round_trip(u16::MAX), u16::MAX);
+        assert_eq!(round_trip(u64::MAX), u64::MAX);
+        assert_eq!(round_trip('Ω'), 'Ω');
+        assert_eq!(char::from_u64(0xD800), None);
+
+        for value in [0, 1, -1, 63, -64, i8::MIN as i32, i32::MIN, i32::MAX] {
+            assert_eq!(round_trip(value), value);
+        }
+        assert_eq!(round_trip(i64::MIN), i64::MIN);
+        assert_eq!(round_trip(i8::MIN), i8::MIN);
+    }
+
+    #[test]
+    fn test_signed_values_are_zig_zag_encoded() {
+        let encoded: Vec<u64> = [0i8, -1, 1, -2, 2, i8::MAX, i8::MIN]
+            .into_iter()
+            .map(FieldType::to_u64)
+            .collect();
+        assert_eq!(encoded, vec![0, 1, 2, 3, 4, 254, 255]);
+    }
+}
@@ -1,7 +1,11 @@
-use crate::core::ChunkPosition;
+use crate::core::{BlockPosition, ChunkPosition};
+#[cfg(feature = "persistence")]
+use crate::{compression::Compression, format::ChunkSchema};
+#[cfg(feature = "persistence")]
 use bincode::error::{DecodeError, EncodeError};
 use chroma::BoundsError;
-use std::io;
+#[cfg(feature = "persistence")]
+use std::{io, path::PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -10,6 +14,20 @@ pub enum AccessError {
     ChunkAccess(#[from] ChunkAccessError),
     #[error(transparent)]
     Bounds(#[from] BoundsError),
+    #[error("Value {value} does not fit the {bits} bits of field {field}.")]
+    ValueOutOfRange {
+        field: &'static str,
+        value: u64,
+        bits: u8,
+    },
+    #[error("Value {value} of field {field} does not decode to its type.")]
+    InvalidValue { field: &'static str, value: u64 },
+    #[error("Position {pos:?} is outside the world, z must be within {min_z}..{max_z}.")]
+    OutsideWorld {
+        pos: BlockPosition,
+        min_z: i32,
+        max_z: i32,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -24,6 +42,90 @@ pub enum ChunkOverwriteError {
     ChunkAlreadyLoaded(ChunkPosition),
 }
 
+#[cfg(feature = "persistence")]
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error(
+        "Chunk was saved with schema version {found}, newer than the current version {current}."
+    )]
+    NewerVersion { found: u32, current: u32 },
+    #[error("No migration is declared from schema version {0}.")]
+    MissingMigration(u32),
+    #[error("Field {0} does not exist at this point of the migration.")]
+    UnknownField(String),
+    #[error("Field {0} already exists.")]
+    DuplicateField(String),
+    #[error("Field {0} is stored but not declared in the world.")]
+    UnexpectedField(String),
+    #[error("Found a subchunk with {found} sections for {expected} fields.")]
+    SectionCount { expected: usize, found: usize },
+    #[error("Default {default} of field {field} does not fit its {bits} bits.")]
+    DefaultOutOfRange {
+        field: String,
+        default: u64,
+        bits: u8,
+    },
+    #[error("Field {field} cannot shrink from {from} to {to} bits.")]
+    Narrowing { field: String, from: u8, to: u8 },
+    #[error("Field {field} is stored with {found} bits but declared with {expected}.")]
+    BitsMismatch {
+        field: String,
+        expected: u8,
+        found: u8,
+    },
+    #[error(transparent)]
+    Bounds(#[from] BoundsError),
+}
+
+#[cfg(feature = "persistence")]
+#[derive(Debug, Error)]
+pub enum CompressionError {
+    #[error("Compression {0:?} is not enabled in this build.")]
+    Unsupported(Compression),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[cfg(feature = "persistence")]
+#[derive(Debug, Error)]
+pub enum HeaderError {
+    #[error("Data does not start with the chunk file magic.")]
+    BadMagic,
+    #[error("Data is a chunk written before chunk files had a header.")]
+    LegacyFormat,
+    #[error("Chunk format version {0} is not supported.")]
+    UnsupportedVersion(u16),
+    #[error("Data ends before the end of the chunk file prefix.")]
+    Truncated,
+    #[error("Checksum {found:#010x} does not match the stored checksum {expected:#010x}.")]
+    ChecksumMismatch { expected: u32, found: u32 },
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// Reason a stored chunk was considered corrupted.
+#[cfg(feature = "persistence")]
+#[derive(Debug, Error)]
+pub enum CorruptionError {
+    #[error(transparent)]
+    Header(#[from] HeaderError),
+    #[error(transparent)]
+    Compression(#[from] CompressionError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error("Found {found} subchunks instead of {expected}.")]
+    SubchunkCount { expected: usize, found: usize },
+    #[error("Found a subchunk with {found} sections for {expected} fields.")]
+    SectionCount { expected: usize, found: usize },
+    #[error("Field {0} is listed more than once in the header.")]
+    DuplicateField(String),
+    #[error("Region entry of {len} bytes at sector {sector} lies outside the region file.")]
+    RegionEntry { sector: u32, len: u32 },
+    #[error("Region file of {len} bytes ends inside its header.")]
+    RegionHeader { len: u64 },
+}
+
+#[cfg(feature = "persistence")]
 #[derive(Debug, Error)]
 pub enum ChunkStoreError {
     #[error(transparent)]
@@ -36,4 +138,45 @@ pub enum ChunkStoreError {
     Encode(#[from] EncodeError),
     #[error(transparent)]
     Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Compression(#[from] CompressionError),
+    #[error("Chunk {0:?} has never been saved.")]
+    NotStored(ChunkPosition),
+    #[error("World has no chunk generator.")]
+    NoGenerator,
+    #[error("Chunk {pos:?} has an invalid header: {source}")]
+    InvalidHeader {
+        pos: ChunkPosition,
+        #[source]
+        source: HeaderError,
+    },
+    #[error("Chunk {pos:?} is corrupted: {source}")]
+    Corrupted {
+        pos: ChunkPosition,
+        /// File the damaged data was read from, if the storage is file based.
+        path: Option<PathBuf>,
+        #[source]
+        source: CorruptionError,
+    },
+    #[error("Chunk {pos:?} was saved with schema {found:?} but the world expects {expected:?}.")]
+    SchemaMismatch {
+        pos: ChunkPosition,
+        expected: ChunkSchema,
+        found: ChunkSchema,
+    },
+    #[error("Chunk {pos:?} could not be migrated: {source}")]
+    Migration {
+        pos: ChunkPosition,
+        #[source]
+        source: MigrationError,
+    },
+}
+
+/// Chunks that failed to save while saving or shutting down a whole world.
+/// They stay loaded, so the operation can be retried.
+#[cfg(feature = "persistence")]
+#[derive(Debug, Error)]
+#[error("Failed to save {} chunks.", .failed.len())]
+pub struct SaveAllError {
+    pub failed: Vec<(ChunkPosition, ChunkStoreError)>,
 }
@@ -0,0 +1,242 @@
+use crate::{compression::Compression, core::FieldInfo, error::HeaderError};
+use bincode::{
+    config,
+    error::EncodeError,
+    serde::{decode_from_slice, encode_into_std_write},
+};
+use serde::{Deserialize, Serialize};
+
+/// Magic bytes every chunk file starts with.
+pub const CHUNK_MAGIC: [u8; 4] = *b"TDCK";
+
+/// Version of the chunk file layout written by this crate.
+pub const FORMAT_VERSION: u16 = 6;
+
+const CHECKSUM_OFFSET: usize = CHUNK_MAGIC.len() + size_of::<u16>();
+const PREFIX_LEN: usize = CHECKSUM_OFFSET + size_of::<u32>();
+
+/// Identifies the world definition a chunk was written with.
+/// Chunks only decode correctly into a world with an identical schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkSchema {
+    pub chunk_width: u32,
+    pub chunk_height: u32,
+    pub chunk_depth: u32,
+    pub subchunk_depth: u32,
+    /// Lowest z coordinate, declared with `min_z` in `world!`.
+    pub min_z: i32,
+    /// Hash of the declared field names and bit widths, see [`fingerprint`].
+    pub fingerprint: u64,
+}
+
+impl ChunkSchema {
+    /// Returns true if both schemas describe chunks of the same dimensions,
+    /// which is required for migrating between them.
+    pub fn same_layout(&self, other: &Self) -> bool {
+        self.chunk_width == other.chunk_width
+            && self.chunk_height == other.chunk_height
+            && self.chunk_depth == other.chunk_depth
+            && self.subchunk_depth == other.subchunk_depth
+            && self.min_z == other.min_z
+    }
+}
+
+/// Header stored in front of every encoded chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkHeader {
+    pub schema: ChunkSchema,
+    /// Version declared with `schema_version` in `world!`, used to pick migrations.
+    pub schema_version: u32,
+    /// Fields in the order their sections are stored in the body.
+    pub fields: Vec<FieldInfo>,
+    /// Codec the body was compressed with.
+    pub compression: Compression,
+    /// Number of generation stages the chunk completed.
+    pub generation_stage: u8,
+}
+
+/// Hashes field names and bit widths in declaration order with 64 bit FNV-1a.
+/// Stable across builds and platforms, unlike the hashers used for maps.
+pub const fn fingerprint(names: &[&str], bits: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash: u64 = OFFSET_BASIS;
+    let mut i: usize = 0;
+
+    while i < names.len() {
+        let name: &[u8] = names[i].as_bytes();
+        let mut j: usize = 0;
+
+        while j < name.len() {
+            hash = (hash ^ name[j] as u64).wrapping_mul(PRIME);
+            j += 1;
+        }
+
+        // separator so adjacent names cannot run together
+        hash = (hash ^ 0xff).wrapping_mul(PRIME);
+        hash = (hash ^ bits[i] as u64).wrapping_mul(PRIME);
+        i += 1;
+    }
+
+    hash
+}
+
+/// Prefixes the encoded chunk body with the magic, format version, checksum and header.
+/// The CRC32 checksum covers the header and body.
+pub fn encode_chunk_file(header: &ChunkHeader, body: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    let mut data: Vec<u8> = Vec::with_capacity(PREFIX_LEN + 32 + body.len());
+    data.extend_from_slice(&CHUNK_MAGIC);
+    data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    data.extend_from_slice(&[0; size_of::<u32>()]);
+    encode_into_std_write(header, &mut data, config::standard())?;
+    data.extend_from_slice(body);
+
+    let checksum: u32 = crc32fast::hash(&data[PREFIX_LEN..]);
+    data[CHECKSUM_OFFSET..PREFIX_LEN].copy_from_slice(&checksum.to_le_bytes());
+    Ok(data)
+}
+
+/// Splits an encoded chunk file into its header and body after verifying its checksum.
+pub fn decode_chunk_file(data: &[u8]) -> Result<(ChunkHeader, &[u8]), HeaderError> {
+    if !data.starts_with(&CHUNK_MAGIC) {
+        // headerless chunks start with the option tag of their first subchunk
+        return Err(match data.first() {
+            Some(0 | 1) => HeaderError::LegacyFormat,
+            _ => HeaderError::BadMagic,
+        });
+    }
+
+    if data.len() < CHECKSUM_OFFSET {
+        return Err(HeaderError::Truncated);
+    }
+
+    let version: u16 = u16::from_le_bytes([data[CHUNK_MAGIC.len()], data[CHUNK_MAGIC.len() + 1]]);
+
+    if version != FORMAT_VERSION {
+        return Err(HeaderError::UnsupportedVersion(version));
+    }
+
+    if data.len() < PREFIX_LEN {
+        return Err(HeaderError::Truncated);
+    }
+
+    let expected: u32 = u32::from_le_bytes(data[CHECKSUM_OFFSET..PREFIX_LEN].try_into().unwrap());
+    let found: u32 = crc32fast::hash(&data[PREFIX_LEN..]);
+
+    if expected != found {
+        return Err(HeaderError::ChecksumMismatch { expected, found });
+    }
+
+    let (header, len): (ChunkHeader, usize) =
+        decode_from_slice(&data[PREFIX_LEN..], config::standard())?;
+
+    Ok((header, &data[PREFIX_LEN + len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: ChunkSchema = ChunkSchema {
+        chunk_width: 16,
+        chunk_height: 16,
+        chunk_depth: 256,
+        subchunk_depth: 16,
+        min_z: 0,
+        fingerprint: fingerprint(&["Block", "SkyLight"], &[8, 4]),
+    };
+
+    #[test]
+    fn test_fingerprint_tracks_names_bits_and_order() {
+        let base: u64 = fingerprint(&["Block", "SkyLight"], &[8, 4]);
+
+        assert_eq!(base, SCHEMA.fingerprint);
+        assert_ne!(base, fingerprint(&["Block", "SkyLight"], &[8, 5]));
+        assert_ne!(base, fingerprint(&["SkyLight", "Block"], &[4, 8]));
+        assert_ne!(base, fingerprint(&["Block", "Light"], &[8, 4]));
+        assert_ne!(
+            fingerprint(&["ab", "c"], &[1, 1]),
+            fingerprint(&["a", "bc"], &[1, 1])
+        );
+    }
+
+    #[test]
+    fn test_chunk_file_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let header: ChunkHeader = ChunkHeader {
+            schema: SCHEMA,
+            schema_version: 3,
+            fields: vec![FieldInfo {
+                name: "Block".to_string(),
+                bits: 8,
+                default: 0,
+            }],
+            compression: Compression::Lz4,
+            generation_stage: 2,
+        };
+        let data: Vec<u8> = encode_chunk_file(&header, &[1, 2, 3])?;
+
+        let (decoded, body) = decode_chunk_file(&data)?;
+        assert_eq!(decoded, header);
+        assert_eq!(body, &[1, 2, 3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_detects_damaged_data() -> Result<(), EncodeError> {
+        let header: ChunkHeader = ChunkHeader {
+            schema: SCHEMA,
+            schema_version: 0,
+            fields: Vec::new(),
+            compression: Compression::None,
+            generation_stage: 0,
+        };
+        let data: Vec<u8> = encode_chunk_file(&header, &[7; 64])?;
+
+        let mut flipped: Vec<u8> = data.clone();
+        flipped[data.len() - 10] ^= 0x04;
+        assert!(matches!(
+            decode_chunk_file(&flipped),
+            Err(HeaderError::ChecksumMismatch { .. })
+        ));
+
+        assert!(matches!(
+            decode_chunk_file(&data[..data.len() - 1]),
+            Err(HeaderError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            decode_chunk_file(&data[..PREFIX_LEN - 1]),
+            Err(HeaderError::Truncated)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_foreign_data() {
+        assert!(matches!(
+            decode_chunk_file(&[0; 32]),
+            Err(HeaderError::LegacyFormat)
+        ));
+        assert!(matches!(
+            decode_chunk_file(&[1, 0, 5]),
+            Err(HeaderError::LegacyFormat)
+        ));
+        assert!(matches!(
+            decode_chunk_file(b"TD"),
+            Err(HeaderError::BadMagic)
+        ));
+        assert!(matches!(
+            decode_chunk_file(b"TDCK\x06"),
+            Err(HeaderError::Truncated)
+        ));
+
+        let mut data: Vec<u8> = CHUNK_MAGIC.to_vec();
+        data.extend_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode_chunk_file(&data),
+            Err(HeaderError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+}
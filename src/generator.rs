@@ -0,0 +1,152 @@
+use crate::{
+    core::{BlockPosition, ChunkPosition},
+    error::AccessError,
+    noise::{Fbm, Noise, Perlin},
+};
+
+/// Produces new chunks for positions that were never saved.
+///
+/// Generic over the chunk type generated by `world!`, so a generator is written for one world definition.
+/// Generation runs on tokio's blocking thread pool, possibly for many positions at once,
+/// or on the calling thread when using the blocking API.
+pub trait ChunkGenerator<C>: Send + Sync {
+    /// Returns the seed this generator was created with.
+    fn seed(&self) -> u64;
+
+    /// Generates the chunk at the passed position.
+    /// Must only depend on the seed and position, so regenerating a chunk yields the same result.
+    /// Fails if a generated value does not fit its field.
+    fn generate(&self, pos: ChunkPosition) -> Result<C, AccessError>;
+}
+
+/// Generation stage recorded for chunks that are complete, such as those made by a [`ChunkGenerator`].
+/// Every pipeline stage counts as done for them.
+pub const FULLY_GENERATED: u8 = u8::MAX;
+
+/// One step of a multi-stage generation pipeline, such as shaping, carving, decoration or lighting.
+///
+/// Generic over the world type generated by `world!`, as stages may write into neighboring chunks.
+/// Each chunk records how many stages it completed, so a stage runs once per chunk.
+///
+/// Chunks whose radii overlap never run a stage at the same time, but their order is unspecified.
+/// Writes into neighboring chunks must therefore commute, such as only ever raising a value,
+/// for the same seed to give the same result.
+pub trait GenerationStage<W>: Send + Sync {
+    /// Returns how many chunks around the generated one this stage may read or write.
+    /// Every chunk within it has completed all earlier stages before this one runs.
+    fn radius(&self) -> u32 {
+        0
+    }
+
+    /// Runs the stage on the chunk at the passed position.
+    fn apply(&self, world: &W, pos: ChunkPosition) -> Result<(), AccessError>;
+}
+
+/// Dimensions of a chunk type, implemented by the chunk generated by `world!`.
+pub trait ChunkShape: Default + Send + 'static {
+    const WIDTH: usize;
+    const HEIGHT: usize;
+    const DEPTH: usize;
+    /// Lowest local z coordinate, below zero for worlds with negative depths.
+    const MIN_Z: i32 = 0;
+}
+
+/// Function filling a single block of a generated chunk, taking local positions.
+type FillFn<C> = dyn Fn(&mut C, BlockPosition) -> Result<(), AccessError> + Send + Sync;
+
+// -- HeightmapGenerator --
+
+/// Reference generator filling every column up to a noise surface.
+///
+/// The surface of a column lies `base_height + amplitude * noise(x * scale, y * scale)` blocks above the bottom of the chunk,
+/// and every block below it is passed to the fill function, e.g. `|chunk: &mut Chunk, pos| chunk.set_block(pos, 1)`.
+/// Generation fails with the first error the fill function returns.
+pub struct HeightmapGenerator<C, N = Fbm<Perlin>> {
+    seed: u64,
+    noise: N,
+    base_height: f64,
+    amplitude: f64,
+    scale: f64,
+    fill: Box<FillFn<C>>,
+}
+
+impl<C: ChunkShape> HeightmapGenerator<C> {
+    /// Creates a generator sampling 5 octaves of Perlin noise with the passed seed.
+    pub fn new(
+        seed: u64,
+        fill: impl Fn(&mut C, BlockPosition) -> Result<(), AccessError> + Send + Sync + 'static,
+    ) -> Self {
+        Self::with_noise(seed, Fbm::new(Perlin::new(seed)).octaves(5), fill)
+    }
+}
+
+impl<C: ChunkShape, N: Noise> HeightmapGenerator<C, N> {
+    /// Creates a generator sampling the passed noise.
+    /// Surfaces vary by a quarter of the chunk depth around its middle by default.
+    pub fn with_noise(
+        seed: u64,
+        noise: N,
+        fill: impl Fn(&mut C, BlockPosition) -> Result<(), AccessError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            seed,
+            noise,
+            base_height: C::DEPTH as f64 / 2.0,
+            amplitude: C::DEPTH as f64 / 4.0,
+            scale: 1.0 / 128.0,
+            fill: Box::new(fill),
+        }
+    }
+
+    /// Sets the average surface height.
+    pub fn base_height(mut self, base_height: f64) -> Self {
+        self.base_height = base_height;
+        self
+    }
+
+    /// Sets how far the surface rises above and sinks below the base height.
+    pub fn amplitude(mut self, amplitude: f64) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Sets the factor block coordinates are multiplied by before sampling the noise.
+    /// Smaller values give wider hills.
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the number of filled blocks in the column at the passed global coordinates,
+    /// counted from the bottom of the chunk and clamped to its depth.
+    pub fn height(&self, x: i32, y: i32) -> usize {
+        let noise: f64 = self
+            .noise
+            .sample2(x as f64 * self.scale, y as f64 * self.scale);
+        let height: f64 = (self.base_height + self.amplitude * noise).floor();
+        height.clamp(0.0, C::DEPTH as f64) as usize
+    }
+}
+
+impl<C: ChunkShape, N: Noise> ChunkGenerator<C> for HeightmapGenerator<C, N> {
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn generate(&self, pos: ChunkPosition) -> Result<C, AccessError> {
+        let mut chunk: C = C::default();
+
+        for x in 0..C::WIDTH as i32 {
+            for y in 0..C::HEIGHT as i32 {
+                let height: usize =
+                    self.height(pos.x * C::WIDTH as i32 + x, pos.y * C::HEIGHT as i32 + y);
+
+                for z in C::MIN_Z..C::MIN_Z + height as i32 {
+                    (self.fill)(&mut chunk, BlockPosition::new(x, y, z))?;
+                }
+            }
+        }
+
+        Ok(chunk)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::core::{ChunkPosition, ChunkState, PositionHasher};
+use dashmap::{DashMap, mapref::entry::Entry};
+#[cfg(feature = "async-io")]
+use std::pin::pin;
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "async-io")]
+use tokio::sync::Notify;
+
+/// A load or save of a single chunk that other callers can wait on.
+struct Operation {
+    state: ChunkState,
+    done: Mutex<bool>,
+    /// Wakes blocking waiters.
+    released: Condvar,
+    /// Wakes async waiters.
+    #[cfg(feature = "async-io")]
+    notify: Notify,
+}
+
+/// Tracks the chunks currently being loaded or saved,
+/// so only one operation per position runs at a time.
+#[derive(Default)]
+pub struct InFlight {
+    operations: DashMap<ChunkPosition, Arc<Operation>, PositionHasher>,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the state of the operation running on the passed chunk, if any.
+    pub fn state(&self, pos: ChunkPosition) -> Option<ChunkState> {
+        self.operations.get(&pos).map(|operation| operation.state)
+    }
+
+    /// Claims the passed chunk for an operation.
+    /// Returns a waiter for the running operation if the chunk is already claimed.
+    pub fn begin(
+        &self,
+        pos: ChunkPosition,
+        state: ChunkState,
+    ) -> Result<InFlightGuard<'_>, InFlightWaiter> {
+        match self.operations.entry(pos) {
+            Entry::Occupied(entry) => Err(InFlightWaiter {
+                operation: Arc::clone(entry.get()),
+            }),
+            Entry::Vacant(entry) => {
+                let operation: Arc<Operation> = Arc::new(Operation {
+                    state,
+                    done: Mutex::new(false),
+                    released: Condvar::new(),
+                    #[cfg(feature = "async-io")]
+                    notify: Notify::new(),
+                });
+                entry.insert(Arc::clone(&operation));
+
+                Ok(InFlightGuard {
+                    in_flight: self,
+                    pos,
+                    operation,
+                })
+            }
+        }
+    }
+
+    /// Waits until no other operation runs on the passed chunk, then claims it.
+    #[cfg(feature = "async-io")]
+    pub async fn claim(&self, pos: ChunkPosition, state: ChunkState) -> InFlightGuard<'_> {
+        loop {
+            match self.begin(pos, state) {
+                Ok(guard) => return guard,
+                Err(waiter) => waiter.wait().await,
+            }
+        }
+    }
+
+    /// Blocks the thread until no other operation runs on the passed chunk, then claims it.
+    pub fn claim_blocking(&self, pos: ChunkPosition, state: ChunkState) -> InFlightGuard<'_> {
+        loop {
+            match self.begin(pos, state) {
+                Ok(guard) => return guard,
+                Err(waiter) => waiter.wait_blocking(),
+            }
+        }
+    }
+}
+
+/// Releases its chunk and wakes all waiters when dropped,
+/// including when the owning future is cancelled.
+pub struct InFlightGuard<'a> {
+    in_flight: &'a InFlight,
+    pos: ChunkPosition,
+    operation: Arc<Operation>,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight
+            .operations
+            .remove_if(&self.pos, |_, operation| {
+                Arc::ptr_eq(operation, &self.operation)
+            });
+        *self.operation.done.lock().unwrap() = true;
+        self.operation.released.notify_all();
+        #[cfg(feature = "async-io")]
+        self.operation.notify.notify_waiters();
+    }
+}
+
+/// Handle to an operation started by another caller.
+pub struct InFlightWaiter {
+    operation: Arc<Operation>,
+}
+
+impl InFlightWaiter {
+    /// Returns what the running operation is doing.
+    pub fn state(&self) -> ChunkState {
+        self.operation.state
+    }
+
+    /// Waits until the running operation has finished, successfully or not.
+    #[cfg(feature = "async-io")]
+    pub async fn wait(self) {
+        let mut notified = pin!(self.operation.notify.notified());
+        // registers before checking, so a release in between is not missed
+        notified.as_mut().enable();
+
+        if *self.operation.done.lock().unwrap() {
+            return;
+        }
+
+        notified.await;
+    }
+
+    /// Blocks the thread until the running operation has finished, successfully or not.
+    pub fn wait_blocking(self) {
+        let mut done = self.operation.done.lock().unwrap();
+
+        while !*done {
+            done = self.operation.released.wait(done).unwrap();
+        }
+    }
+}
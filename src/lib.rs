@@ -1,24 +1,138 @@
+//! Chunked voxel storage generated per world by the [`world!`] macro.
+//!
+//! # Features
+//!
+//! | Feature       | Default | Enables                                                                   |
+//! |---------------|---------|---------------------------------------------------------------------------|
+//! | `async-io`    | yes     | Async persistence, autosave, viewer tickets and the evictor, using tokio. Implies `persistence`. |
+//! | `persistence` | no      | Chunk storage backends and the blocking persistence API, using bincode and crc32fast. Implies `serde`. |
+//! | `serde`       | no      | `Serialize` and `Deserialize` for chunks and world metadata.              |
+//! | `lz4`         | yes     | LZ4 chunk compression.                                                    |
+//! | `zstd`        | no      | Zstandard chunk compression.                                              |
+//! | `derive`      | yes     | `#[derive(FieldType)]` for fieldless enums.                               |
+//! | `ahash`       | yes     | aHash for maps keyed by chunk position instead of the standard SipHash.   |
+//!
+//! Without default features only the in-memory get and set API remains. It depends on
+//! `glam`, `chroma` and `dashmap`, plus `paste`, which `world!` needs to name the generated
+//! accessors, and `thiserror`, which derives the error types. Both are always required.
+//! `cargo check --no-default-features` builds this core alone.
+
 #![allow(dead_code)]
 
+// lets code generated by the derive macro name this crate from inside it too
+#[cfg(feature = "derive")]
+extern crate self as terrain_data;
+
+#[cfg(feature = "async-io")]
+pub mod autosave;
+pub mod cache;
+#[cfg(feature = "persistence")]
+pub mod compression;
 pub mod core;
 pub mod error;
+#[cfg(feature = "persistence")]
+pub mod format;
+pub mod generator;
+pub mod inflight;
+#[cfg(feature = "persistence")]
+pub mod migration;
+pub mod noise;
+#[cfg(feature = "async-io")]
+pub mod parallel;
+pub mod persistence;
 pub mod prelude;
+#[cfg(feature = "persistence")]
+pub mod region;
+#[cfg(feature = "persistence")]
+pub mod storage;
+#[cfg(feature = "async-io")]
+pub mod tickets;
 
 #[doc(hidden)]
 pub mod __internal_prelude {
+    #[cfg(feature = "ahash")]
     pub use ahash;
+    #[cfg(feature = "persistence")]
     pub use bincode;
     pub use chroma;
     pub use dashmap;
     pub use glam;
-    pub use itertools;
     pub use paste;
+    #[cfg(feature = "serde")]
     pub use serde;
     pub use std;
     pub use thiserror;
+    #[cfg(feature = "async-io")]
     pub use tokio;
 }
 
+/// Expands to the passed items only if the `async-io` feature is enabled,
+/// as `cfg` attributes inside `world!` would check the features of the calling crate.
+#[cfg(feature = "async-io")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_async_io {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "async-io"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_async_io {
+    ($($item:item)*) => {};
+}
+
+/// Expands to the passed items only if the `persistence` feature is enabled.
+#[cfg(feature = "persistence")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_persistence {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "persistence"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_persistence {
+    ($($item:item)*) => {};
+}
+
+/// Derives `Serialize` and `Deserialize` for the passed struct if the `serde` feature is enabled.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __derive_serde {
+    ($item:item) => {
+        #[derive(
+            $crate::__internal_prelude::serde::Serialize,
+            $crate::__internal_prelude::serde::Deserialize,
+        )]
+        $item
+    };
+}
+
+/// Strips the `serde` field attributes of the passed struct instead, as nothing would accept them.
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __derive_serde {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $( $(#[serde $($serde:tt)*])* $field_vis:vis $field:ident: $field_type:ty ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $( $field_vis $field: $field_type ),*
+        }
+    };
+}
+
 /// Macro to create a new world.
 ///
 /// # Examples
@@ -50,12 +164,174 @@ pub mod __internal_prelude {
 ///     world.set_is_exposed(pos_1, false)?;
 ///     world.set_is_exposed(pos_2, true)?;
 ///
-///     assert_eq!(world.is_exposed(pos_1)?, false);
-///     assert_eq!(world.is_exposed(pos_2)?, true);
+///     assert!(!world.is_exposed(pos_1)?);
+///     assert!(world.is_exposed(pos_2)?);
 ///
 ///     Ok(())
 /// }
 /// ```
+///
+/// # Migrations
+///
+/// Worlds without a `schema_version` are at version 0.
+/// When the field list changes, bump the version and declare how chunks
+/// saved with each older version are upgraded to the next one.
+/// Defaults of added fields are raw stored values.
+///
+/// ```
+/// use terrain_data::prelude::*;
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     schema_version: 2,
+///     migrations: {
+///         0 => [rename(Light, SkyLight), widen(Block, bits: 8)],
+///         1 => [add(Exposed, bits: 1, default: 0), remove(Temperature)],
+///     },
+///     Block r#as block: u8 = 8,
+///     SkyLight r#as sky_light: u8 = 5,
+///     Exposed r#as is_exposed: bool = 1,
+/// }
+///
+/// assert_eq!(SCHEMA_VERSION, 2);
+/// ```
+///
+/// An added field whose default does not fit its bits fails to compile.
+///
+/// ```compile_fail
+/// use terrain_data::prelude::*;
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     schema_version: 1,
+///     migrations: {
+///         0 => [add(Exposed, bits: 1, default: 5)],
+///     },
+///     Exposed r#as is_exposed: bool = 1,
+/// }
+/// ```
+///
+/// # Depth range
+///
+/// Block z coordinates range over `MIN_Z..MAX_Z`, starting at 0 unless `min_z` is declared
+/// right after `num_subchunks`. Positions outside the range are rejected with [`error::AccessError::OutsideWorld`].
+///
+/// ```
+/// use terrain_data::prelude::*;
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     min_z: -64,
+///     Block r#as block: u8 = 8,
+/// }
+///
+/// assert_eq!((MIN_Z, MAX_Z), (-64, 192));
+/// assert!(World::contains(BlockPosition::new(0, 0, -64)));
+/// assert!(!World::contains(BlockPosition::new(0, 0, 192)));
+/// ```
+///
+/// # Field widths
+///
+/// Fields can have any type implementing [`core::FieldType`], which includes every primitive
+/// integer up to 64 bits, `bool` and `char`. Signed integers are zig-zag encoded,
+/// so a 5 bit `i8` field holds `-16..=15`.
+///
+/// Setting a value wider than its field's bits returns [`error::AccessError::ValueOutOfRange`],
+/// and declaring more bits than the field's type can hold fails to compile.
+///
+/// ```compile_fail
+/// use terrain_data::prelude::*;
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     Exposed r#as is_exposed: bool = 2,
+/// }
+/// ```
+///
+/// # Field defaults
+///
+/// Blocks read back as 0 until they are set, unless the field declares a default.
+/// Sections store values relative to it, so regions left at the default allocate nothing.
+/// Defaults are checked at compile time, and a default that does not fit its field's bits fails to compile.
+/// The check casts the default with `as`, so `#[default]` is limited to primitive fields and enums
+/// deriving [`core::FieldType`]. Other types, such as newtypes, fail to compile with a non-primitive cast
+/// error, and manual enum impls must encode every variant as its discriminant.
+///
+/// ```
+/// use terrain_data::prelude::*;
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     Block r#as block: u8 = 8,
+///     #[default = 15]
+///     SkyLight r#as sky_light: u8 = 4,
+/// }
+///
+/// let chunk: Chunk = Chunk::default();
+/// assert_eq!(chunk.sky_light(BlockPosition::ZERO).unwrap(), 15);
+/// ```
+///
+/// ```compile_fail
+/// use terrain_data::prelude::*;
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     #[default = 20]
+///     SkyLight r#as sky_light: u8 = 4,
+/// }
+/// ```
+///
+/// # Enum fields
+///
+/// Fieldless enums can derive [`core::FieldType`] with the `derive` feature. Field types are
+/// named from inside the generated module, so enums declared next to the world need a full path.
+/// Raw values matching no variant read back as the `#[field_type(fallback)]` variant if there is one
+/// and as [`error::AccessError::InvalidValue`] otherwise.
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// use terrain_data::prelude::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, FieldType)]
+/// pub enum Facing {
+///     North,
+///     East,
+///     South,
+///     West,
+/// }
+///
+/// world! {
+///     chunk_width: 16,
+///     chunk_height: 16,
+///     subchunk_depth: 16,
+///     num_subchunks: 16,
+///     Facing r#as facing: crate::Facing = 2,
+/// }
+///
+/// fn main() {
+///     let mut chunk: Chunk = Chunk::default();
+///     chunk.set_facing(BlockPosition::ZERO, Facing::South).unwrap();
+///     assert_eq!(chunk.facing(BlockPosition::ZERO).unwrap(), Facing::South);
+/// }
+/// ```
 #[macro_export]
 macro_rules! world {
     (
@@ -63,7 +339,18 @@ macro_rules! world {
         chunk_height: $chunk_height:expr,
         subchunk_depth: $subchunk_depth:expr,
         num_subchunks: $num_subchunks:expr,
+        $(min_z: $min_z:expr,)?
+        schema_version: $schema_version:expr,
+        migrations: {
+            $(
+                $from_version:literal => [
+                    $( $step:ident ( $($step_args:tt)* ) ),* $(,)?
+                ]
+            ),*
+            $(,)?
+        },
         $(
+            $(#[default = $default:expr])?
             $field_name_enum:ident r#as $field_name_method:ident: $field_type:ty = $bits_per_item:expr
         ),*
         $(,)?
@@ -72,40 +359,88 @@ macro_rules! world {
 
         mod __internal_world {
             use $crate::__internal_prelude::{
-                ahash::AHasher,
-                bincode::{
-                    config,
-                    serde as bincode_serde,
-                    serde::encode_to_vec,
-                },
                 chroma::{BoundsError, Section},
                 dashmap::{
                     DashMap,
                     mapref::one::{Ref, RefMut},
-                    mapref::entry::Entry,
+                    mapref::entry::{Entry, VacantEntry},
                 },
-                itertools::iproduct,
                 paste::paste,
-                serde::{Deserialize, Serialize},
-                std::{hash::BuildHasherDefault, path::PathBuf},
-                tokio::{fs, io::AsyncWriteExt}
+                std::{
+                    mem,
+                    sync::{
+                        Arc,
+                        atomic::{AtomicU64, Ordering},
+                    },
+                },
             };
 
+            $crate::__cfg_persistence! {
+                use $crate::__internal_prelude::{
+                    bincode::{
+                        config,
+                        serde as bincode_serde,
+                        serde::encode_to_vec,
+                    },
+                    std::{io, path::PathBuf},
+                };
+
+                use $crate::{
+                    cache::CachedChunk,
+                    compression::Compression,
+                    core::{CorruptionPolicy, StorageFormat},
+                    error::{
+                        ChunkStoreError,
+                        CompressionError,
+                        CorruptionError,
+                        HeaderError,
+                        SaveAllError,
+                    },
+                    format::{self, ChunkHeader, ChunkSchema},
+                    generator::FULLY_GENERATED,
+                    migration::{self, Migration, SubchunkRecord},
+                    persistence::StoredChunk,
+                    storage::ChunkStorage,
+                };
+            }
+
+            $crate::__cfg_async_io! {
+                use $crate::__internal_prelude::{
+                    std::{
+                        future::{self, Future, Ready},
+                        panic,
+                        sync::Weak,
+                    },
+                    tokio::task::{self, JoinSet},
+                };
+
+                use $crate::{
+                    autosave::ChunkSaver,
+                    cache::PinGuard,
+                    parallel::run_limited,
+                    storage::BoxFuture,
+                    tickets::ChunkLoader,
+                };
+            }
+
             use $crate::{
+                cache::ChunkCache,
                 core::{
                     BlockPosition,
                     ChunkPosition,
+                    ChunkState,
+                    FieldInfo,
+                    MemoryBudget,
                     FieldType,
-                    CHUNKS_DIR,
+                    PositionHasher,
+                    WorldMetadata,
                     CHUNK_ADJ_OFFSETS,
                     BLOCK_OFFSETS
                 },
-                error::{
-                    AccessError,
-                    ChunkAccessError,
-                    ChunkOverwriteError,
-                    ChunkStoreError
-                }
+                error::{AccessError, ChunkAccessError, ChunkOverwriteError},
+                generator::{ChunkGenerator, ChunkShape, GenerationStage},
+                inflight::{InFlight, InFlightGuard, InFlightWaiter},
+                persistence::{Persistence, PersistenceOptions},
             };
 
             const SUBCHUNK_DEPTH: usize = $subchunk_depth as usize;
@@ -115,24 +450,109 @@ macro_rules! world {
             pub const CHUNK_DEPTH: usize = SUBCHUNK_DEPTH * NUM_SUBCHUNKS;
             pub const CHUNK_VOLUME: usize = CHUNK_WIDTH * CHUNK_HEIGHT * CHUNK_DEPTH;
 
+            /// Lowest z coordinate of the world, the declared `min_z` or 0.
+            pub const MIN_Z: i32 = [$($min_z,)? 0][0];
+            /// One past the highest z coordinate of the world.
+            pub const MAX_Z: i32 = MIN_Z + CHUNK_DEPTH as i32;
+
+            /// Version of the field list, bumped whenever a migration is added.
+            pub const SCHEMA_VERSION: u32 = $schema_version;
+
+            $crate::__cfg_persistence! {
+                /// Declared upgrades from older schema versions.
+                pub const MIGRATIONS: &[Migration] = &[
+                    $(
+                        Migration {
+                            from_version: $from_version,
+                            steps: &[$($crate::__migration_step!($step($($step_args)*))),*],
+                        }
+                    ),*
+                ];
+
+                /// Schema written into every chunk header, checked again when loading.
+                pub const SCHEMA: ChunkSchema = ChunkSchema {
+                    chunk_width: CHUNK_WIDTH as u32,
+                    chunk_height: CHUNK_HEIGHT as u32,
+                    chunk_depth: CHUNK_DEPTH as u32,
+                    subchunk_depth: SUBCHUNK_DEPTH as u32,
+                    min_z: MIN_Z,
+                    fingerprint: format::fingerprint(SectionField::NAME_TABLE, SectionField::BITS_PER_ITEM_TABLE),
+                };
+            }
+
             // -- World --
 
             /// Stores all chunks and marks dirty chunks.
             /// Allows access and modification to them.
-            #[derive(Default)]
+            ///
+            /// Loaded chunks are not saved when the world is dropped, call [`World::shutdown`] first.
             pub struct World {
-                chunks: DashMap<ChunkPosition, Chunk, BuildHasherDefault<AHasher>>,
+                chunks: DashMap<ChunkPosition, Chunk, PositionHasher>,
+                in_flight: InFlight,
+                persistence: Persistence,
+                generator: Option<Arc<dyn ChunkGenerator<Chunk>>>,
+                stages: Vec<Arc<dyn GenerationStage<World>>>,
+                cache: Arc<ChunkCache>,
+            }
+
+            impl Default for World {
+                fn default() -> Self {
+                    Self::builder().build()
+                }
+            }
+
+            impl Drop for World {
+                fn drop(&mut self) {
+                    // wakes the evictor so it notices the world is gone
+                    self.cache.notify();
+                }
             }
 
             impl World {
+                /// Returns a builder for configuring a new world.
+                pub fn builder() -> WorldBuilder {
+                    WorldBuilder::default()
+                }
+
+                /// Returns the generator producing chunks that were never saved, if one was set.
+                #[inline]
+                pub fn generator(&self) -> Option<&Arc<dyn ChunkGenerator<Chunk>>> {
+                    self.generator.as_ref()
+                }
+
+                /// Returns the generation stages in the order they run.
+                #[inline]
+                pub fn stages(&self) -> &[Arc<dyn GenerationStage<World>>] {
+                    &self.stages
+                }
+
+                /// Returns the budget loaded chunks are evicted to stay within.
+                #[inline]
+                pub fn memory_budget(&self) -> MemoryBudget {
+                    self.cache.budget()
+                }
+
+                /// Returns the metadata describing this world definition.
+                pub fn metadata() -> WorldMetadata {
+                    WorldMetadata {
+                        chunk_width: CHUNK_WIDTH as u32,
+                        chunk_height: CHUNK_HEIGHT as u32,
+                        chunk_depth: CHUNK_DEPTH as u32,
+                        min_z: MIN_Z,
+                        schema_version: SCHEMA_VERSION,
+                        fields: SectionField::infos(),
+                    }
+                }
+
                 // getters
 
                 $(
                     #[inline]
                     pub fn $field_name_method(&self, pos: BlockPosition) -> Result<$field_type, AccessError> {
+                        Self::check_bounds(pos)?;
                         let chunk_pos: ChunkPosition = Self::block_to_chunk_pos(pos);
                         let local_pos: BlockPosition = Self::global_to_local_pos(pos);
-                        Ok(self.chunk(chunk_pos)?.$field_name_method(local_pos)?)
+                        self.chunk(chunk_pos)?.$field_name_method(local_pos)
                     }
                 )*
 
@@ -140,17 +560,18 @@ macro_rules! world {
 
                 paste! {
                     $(
-                        #[must_use]
                         #[inline]
                         pub fn [<set_ $field_name_method>](
                             &self,
                             pos: BlockPosition,
                             value: $field_type
                         ) -> Result<(), AccessError> {
+                            Self::check_bounds(pos)?;
                             let chunk_pos: ChunkPosition = Self::block_to_chunk_pos(pos);
                             let local_pos: BlockPosition = Self::global_to_local_pos(pos);
-                            self.chunk_mut(chunk_pos)?.value_mut()
-                                .[<set_$field_name_method>](local_pos, value)?;
+                            let mut chunk = self.chunk_mut(chunk_pos)?;
+                            chunk.[<set_$field_name_method>](local_pos, value)?;
+                            self.recount(&mut chunk);
                             Ok(())
                         }
                     )*
@@ -160,15 +581,36 @@ macro_rules! world {
                 pub fn chunk(
                     &self,
                     pos: ChunkPosition
-                ) -> Result<Ref<ChunkPosition, Chunk>, ChunkAccessError> {
-                    self.chunks.get(&pos).ok_or(ChunkAccessError::ChunkUnloaded(pos))
+                ) -> Result<Ref<'_, ChunkPosition, Chunk>, ChunkAccessError> {
+                    let chunk = self.chunks.get(&pos).ok_or(ChunkAccessError::ChunkUnloaded(pos))?;
+                    if self.cache.is_limited() {
+                        chunk.last_access.store(self.cache.touch(), Ordering::Relaxed);
+                    }
+                    Ok(chunk)
                 }
 
                 #[inline]
                 pub fn chunk_mut(
                     &self,
                     pos: ChunkPosition
-                ) -> Result<RefMut<ChunkPosition, Chunk>, ChunkAccessError> {
+                ) -> Result<RefMut<'_, ChunkPosition, Chunk>, ChunkAccessError> {
+                    let mut chunk = self.chunks.get_mut(&pos).ok_or(ChunkAccessError::ChunkUnloaded(pos))?;
+                    if self.cache.is_limited() {
+                        *chunk.last_access.get_mut() = self.cache.touch();
+                    }
+                    Ok(chunk)
+                }
+
+                /// Like [`Self::chunk`], but leaves the chunk's access time alone,
+                /// so internal work such as saving does not count as using it.
+                #[inline]
+                fn peek_chunk(&self, pos: ChunkPosition) -> Result<Ref<'_, ChunkPosition, Chunk>, ChunkAccessError> {
+                    self.chunks.get(&pos).ok_or(ChunkAccessError::ChunkUnloaded(pos))
+                }
+
+                /// Like [`Self::chunk_mut`], but leaves the chunk's access time alone.
+                #[inline]
+                fn peek_chunk_mut(&self, pos: ChunkPosition) -> Result<RefMut<'_, ChunkPosition, Chunk>, ChunkAccessError> {
                     self.chunks.get_mut(&pos).ok_or(ChunkAccessError::ChunkUnloaded(pos))
                 }
 
@@ -177,20 +619,75 @@ macro_rules! world {
                     self.chunks.contains_key(&pos)
                 }
 
-                /// Sets new given chunk at the passed position.
+                /// Sets new given chunk at the passed position and marks it dirty.
                 /// Returns an error if a chunk is already at the position.
-                #[must_use]
                 #[inline]
                 pub fn add_chunk(&self, pos: ChunkPosition, chunk: Option<Chunk>) -> Result<(), ChunkOverwriteError> {
                     match self.chunks.entry(pos) {
                         Entry::Occupied(_) => Err(ChunkOverwriteError::ChunkAlreadyLoaded(pos)),
                         Entry::Vacant(entry) => {
-                            entry.insert(chunk.unwrap_or(Chunk::default()));
+                            let mut chunk: Chunk = chunk.unwrap_or_default();
+                            chunk.dirty = true;
+                            self.insert_vacant(entry, chunk);
                             Ok(())
                         }
                     }
                 }
 
+                /// Inserts a chunk as just accessed, then wakes the evictor if the world is over budget.
+                fn insert_vacant(&self, entry: VacantEntry<'_, ChunkPosition, Chunk>, mut chunk: Chunk) {
+                    *chunk.last_access.get_mut() = self.cache.touch();
+                    // counted afresh, as the chunk may come from another world
+                    chunk.counted_size = if self.cache.tracks_bytes() { chunk.memory_size() } else { 0 };
+                    self.cache.resize(0, chunk.counted_size);
+                    entry.insert(chunk);
+
+                    if self.cache.is_over_budget(self.chunks.len()) {
+                        self.cache.notify();
+                    }
+                }
+
+                /// Updates the world's tracked bytes with the current size of the passed chunk
+                /// and wakes the evictor if it grew over a byte budget. Does nothing under other budgets.
+                #[inline]
+                fn recount(&self, chunk: &mut Chunk) {
+                    if !self.cache.tracks_bytes() {
+                        return;
+                    }
+
+                    let size: usize = chunk.memory_size();
+
+                    if size != chunk.counted_size {
+                        self.cache.resize(chunk.counted_size, size);
+                        chunk.counted_size = size;
+
+                        if self.cache.is_over_bytes() {
+                            self.cache.notify();
+                        }
+                    }
+                }
+
+                /// Returns the estimated number of bytes used by all loaded chunks.
+                pub fn memory_usage(&self) -> usize {
+                    self.chunks.iter().map(|entry| entry.memory_size()).sum()
+                }
+
+                /// Keeps the chunk at the passed position from being evicted until it is unpinned.
+                /// Chunks can be pinned before they are loaded, and pinning twice needs two unpins.
+                pub fn pin_chunk(&self, pos: ChunkPosition) {
+                    self.cache.pin(pos);
+                }
+
+                /// Releases one pin of the chunk at the passed position. Returns false if it was not pinned.
+                pub fn unpin_chunk(&self, pos: ChunkPosition) -> bool {
+                    self.cache.unpin(pos)
+                }
+
+                /// Returns true if the chunk at the passed position is pinned.
+                pub fn is_pinned(&self, pos: ChunkPosition) -> bool {
+                    self.cache.is_pinned(pos)
+                }
+
                 /// Gets an iter of all chunk positions in a square around the passed origin position.
                 /// Radius of 0 results in 1 position.
                 pub fn positions_in_square(
@@ -198,9 +695,9 @@ macro_rules! world {
                     radius: u32
                 ) -> impl Iterator<Item = ChunkPosition> {
                     let radius: i32 = radius as i32;
-                    iproduct!(-radius..=radius, -radius..=radius).map(
-                        move |(x, y)| origin + ChunkPosition::new(x, y)
-                    )
+                    (-radius..=radius).flat_map(move |x| {
+                        (-radius..=radius).map(move |y| origin + ChunkPosition::new(x, y))
+                    })
                 }
 
                 /// Returns all adjacent chunk offsets.
@@ -227,15 +724,33 @@ macro_rules! world {
                 pub fn chunk_coords(offset: ChunkPosition) -> impl Iterator<Item = BlockPosition> {
                     let base_block_pos: BlockPosition = Self::chunk_to_block_pos(offset);
 
-                    iproduct!(0..CHUNK_WIDTH as i32, 0..CHUNK_HEIGHT as i32, 0..CHUNK_DEPTH as i32).map(
-                        move |(x, y, z)| base_block_pos + BlockPosition::new(x, y, z)
-                    )
+                    (0..CHUNK_WIDTH as i32).flat_map(move |x| {
+                        (0..CHUNK_HEIGHT as i32).flat_map(move |y| {
+                            (0..CHUNK_DEPTH as i32).map(move |z| base_block_pos + BlockPosition::new(x, y, z))
+                        })
+                    })
                 }
 
-                /// Converts a given chunk position to its zero corner block position.
+                /// Converts a given chunk position to its lowest corner block position.
                 #[inline]
                 pub const fn chunk_to_block_pos(pos: ChunkPosition) -> BlockPosition {
-                    BlockPosition::new(pos.x * (CHUNK_WIDTH as i32), pos.y * (CHUNK_HEIGHT as i32), 0)
+                    BlockPosition::new(pos.x * (CHUNK_WIDTH as i32), pos.y * (CHUNK_HEIGHT as i32), MIN_Z)
+                }
+
+                /// Returns true if the passed global position lies within the world's z range.
+                #[inline]
+                pub const fn contains(pos: BlockPosition) -> bool {
+                    pos.z >= MIN_Z && pos.z < MAX_Z
+                }
+
+                /// Returns an error naming the valid z range if the passed global position lies above or below the world.
+                #[inline]
+                pub fn check_bounds(pos: BlockPosition) -> Result<(), AccessError> {
+                    if Self::contains(pos) {
+                        Ok(())
+                    } else {
+                        Err(AccessError::OutsideWorld { pos, min_z: MIN_Z, max_z: MAX_Z })
+                    }
                 }
 
                 /// Gets the chunk position a block position falls into.
@@ -254,287 +769,2715 @@ macro_rules! world {
                     )
                 }
 
-                pub async fn unload_chunk(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
-                    let (_, chunk): (ChunkPosition, Chunk) = self.chunks
-                        .remove(&pos)
-                        .ok_or(AccessError::ChunkAccess(ChunkAccessError::ChunkUnloaded(pos)))?;
-
-                    fs::create_dir_all(CHUNKS_DIR).await?;
-                    let path: PathBuf = PathBuf::from(CHUNKS_DIR).join(format!("{}_{}.bin", pos.x, pos.y));
-                    let mut file: fs::File = fs::File::create(&path).await?;
-
-                    let encoded_data = encode_to_vec(&chunk, config::standard())?;
-
-                    file.write_all(&encoded_data).await?;
-
-                    Ok(())
+                /// Returns the positions of all loaded chunks modified since they were last saved.
+                pub fn dirty_chunks(&self) -> Vec<ChunkPosition> {
+                    self.chunks
+                        .iter()
+                        .filter(|entry| entry.is_dirty())
+                        .map(|entry| *entry.key())
+                        .collect()
                 }
 
-                #[must_use]
-                pub async fn load_chunk(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
-                    if self.is_chunk_at_pos(pos) {
-                        return Err(ChunkStoreError::ChunkOverwrite(ChunkOverwriteError::ChunkAlreadyLoaded(pos)));
+                /// Returns whether the chunk at the passed position is loaded or being loaded or saved.
+                pub fn chunk_state(&self, pos: ChunkPosition) -> ChunkState {
+                    match self.in_flight.state(pos) {
+                        Some(state) => state,
+                        None if self.is_chunk_at_pos(pos) => ChunkState::Loaded,
+                        None => ChunkState::Unloaded,
                     }
-
-                    let path: PathBuf = PathBuf::from(CHUNKS_DIR).join(format!("{}_{}.bin", pos.x, pos.y));
-                    let encoded_data: Vec<u8> = fs::read(&path).await?;
-
-                    let (chunk, _): (Chunk, usize) = bincode_serde::decode_from_slice(
-                        &encoded_data,
-                        config::standard()
-                    )?;
-
-                    self.chunks.insert(pos, chunk);
-
-                    Ok(())
                 }
             }
 
-            // -- Chunk --
-
-            #[derive(Default, Serialize, Deserialize)]
-            pub struct Chunk {
-                subchunks: [Option<Subchunk>; NUM_SUBCHUNKS],
-            }
+            $crate::__cfg_persistence! {
+                impl World {
+                    /// Creates an empty world that persists its chunks under the passed root directory.
+                    pub fn new(root: impl Into<PathBuf>) -> Self {
+                        Self::builder().root(root).build()
+                    }
 
-            impl Chunk {
-                // getters
+                    /// Returns the backend chunks are persisted to.
+                    #[inline]
+                    pub fn storage(&self) -> &Arc<dyn ChunkStorage> {
+                        &self.persistence.storage
+                    }
 
-                $(
+                    /// Returns the codec newly saved chunks are compressed with.
                     #[inline]
-                    pub fn $field_name_method(&self, pos: BlockPosition) -> Result<$field_type, BoundsError> {
-                        let index: usize = Self::subchunk_index(pos.z);
+                    pub fn compression(&self) -> Compression {
+                        self.persistence.compression
+                    }
 
-                        let Some(subchunk_opt) = self.subchunks.get(index) else {
-                            return Err(BoundsError::OutOfBounds(pos));
-                        };
+                    /// Returns the file a chunk at the passed position is saved to, if the storage is file based.
+                    pub fn chunk_path(&self, pos: ChunkPosition) -> Option<PathBuf> {
+                        self.persistence.storage.path(pos)
+                    }
 
-                        subchunk_opt.as_ref().map_or(Ok(<$field_type as FieldType>::from_u64(0)), |s| {
-                            let sub_pos: BlockPosition = Self::local_to_sub(pos);
-                            Ok(s.$field_name_method(sub_pos)?)
-                        })
+                    /// Returns the positions of all loaded chunks.
+                    fn loaded_chunks(&self) -> Vec<ChunkPosition> {
+                        self.chunks.iter().map(|entry| *entry.key()).collect()
                     }
-                )*
 
-                // setters
+                    /// Returns the chunks to evict to fit the memory budget, least recently used first.
+                    fn eviction_victims(&self) -> Vec<ChunkPosition> {
+                        let chunks: Vec<CachedChunk> = self.chunks
+                            .iter()
+                            .map(|entry| CachedChunk {
+                                pos: *entry.key(),
+                                last_access: entry.last_access.load(Ordering::Relaxed),
+                                size: entry.memory_size(),
+                            })
+                            .collect();
+
+                        self.cache.victims(chunks)
+                    }
 
-                paste! {
-                    $(
-                        #[must_use]
-                        #[inline]
-                        pub fn [<set_ $field_name_method>](
-                            &mut self,
-                            pos: BlockPosition,
-                            value: $field_type
-                        ) -> Result<(), BoundsError> {
-                            let index: usize = Self::subchunk_index(pos.z);
+                    /// Counts the chunks an operation succeeded for and lists the ones it failed for.
+                    /// Chunks unloaded since they were listed were already persisted and are skipped.
+                    fn collect_failures(
+                        results: impl IntoIterator<Item = (ChunkPosition, Result<(), ChunkStoreError>)>,
+                    ) -> Result<usize, SaveAllError> {
+                        let mut count: usize = 0;
+                        let mut failed: Vec<(ChunkPosition, ChunkStoreError)> = Vec::new();
+
+                        for (pos, result) in results {
+                            match Self::unless_unloaded(result) {
+                                Ok(true) => count += 1,
+                                Ok(false) => {}
+                                Err(e) => failed.push((pos, e)),
+                            }
+                        }
 
-                            let Some(subchunk_opt) = self.subchunks.get_mut(index) else {
-                                return Err(BoundsError::OutOfBounds(pos));
-                            };
+                        if failed.is_empty() {
+                            Ok(count)
+                        } else {
+                            Err(SaveAllError { failed })
+                        }
+                    }
 
-                            if <$field_type as FieldType>::to_u64(value) == 0 && subchunk_opt.is_none() {
-                                return Ok(()); // return if placement is redundant
+                    /// Lists the chunks an eviction unloaded, attempting all of them and returning the first error.
+                    /// Chunks unloaded since the victims were chosen are skipped.
+                    fn collect_evicted(
+                        results: impl IntoIterator<Item = (ChunkPosition, Result<(), ChunkStoreError>)>,
+                    ) -> Result<Vec<ChunkPosition>, ChunkStoreError> {
+                        let mut evicted: Vec<ChunkPosition> = Vec::new();
+                        let mut result: Result<(), ChunkStoreError> = Ok(());
+
+                        for (pos, outcome) in results {
+                            match Self::unless_unloaded(outcome) {
+                                Ok(true) => evicted.push(pos),
+                                Ok(false) => {}
+                                Err(e) => {
+                                    if result.is_ok() {
+                                        result = Err(e);
+                                    }
+                                }
                             }
+                        }
 
-                            let subchunk: &mut Subchunk = subchunk_opt.get_or_insert_with(|| Subchunk::default());
-                            let sub_pos: BlockPosition = Self::local_to_sub(pos);
+                        result.map(|()| evicted)
+                    }
 
-                            subchunk.[<set_ $field_name_method>](sub_pos, value)?;
+                    /// Returns false instead of an error for a chunk unloaded since it was listed,
+                    /// as unloading it already persisted it.
+                    fn unless_unloaded(result: Result<(), ChunkStoreError>) -> Result<bool, ChunkStoreError> {
+                        match result {
+                            Ok(()) => Ok(true),
+                            Err(ChunkStoreError::Access(AccessError::ChunkAccess(ChunkAccessError::ChunkUnloaded(_)))) => Ok(false),
+                            Err(e) => Err(e),
+                        }
+                    }
 
-                            if subchunk.is_empty() {
-                                *subchunk_opt = None; // set empty subchunks to none
-                            }
+                    fn generate_chunk(generator: &dyn ChunkGenerator<Chunk>, pos: ChunkPosition) -> Result<Chunk, AccessError> {
+                        let mut chunk: Chunk = generator.generate(pos)?;
+                        chunk.generation_stage = FULLY_GENERATED;
+                        Ok(chunk)
+                    }
 
-                            Ok(())
+                    /// Reports storage errors carrying a [`CorruptionError`] as a corrupted chunk,
+                    /// so the corruption policy applies to them like to undecodable data.
+                    fn read_error(&self, pos: ChunkPosition, error: io::Error) -> ChunkStoreError {
+                        if !error.get_ref().is_some_and(|inner| inner.is::<CorruptionError>()) {
+                            return ChunkStoreError::Io(error);
                         }
-                    )*
-                }
 
-                #[inline]
-                const fn subchunk_index(pos_z: i32) -> usize {
-                    (pos_z as usize).div_euclid(SUBCHUNK_DEPTH)
-                }
+                        let kind: io::ErrorKind = error.kind();
 
-                #[inline]
-                const fn local_to_sub(pos: BlockPosition) -> BlockPosition {
-                    BlockPosition::new(pos.x, pos.y, pos.z.rem_euclid(SUBCHUNK_DEPTH as i32))
-                }
-            }
+                        match error.into_inner().map(|inner| inner.downcast::<CorruptionError>()) {
+                            Some(Ok(source)) => ChunkStoreError::Corrupted {
+                                pos,
+                                path: self.persistence.storage.path(pos),
+                                source: *source,
+                            },
+                            Some(Err(inner)) => ChunkStoreError::Io(io::Error::new(kind, inner)),
+                            None => ChunkStoreError::Io(kind.into()),
+                        }
+                    }
 
-            // -- Subchunk --
+                    /// Decodes a stored chunk, naming the file it was read from if it is corrupted.
+                    fn decode_stored(&self, pos: ChunkPosition, data: &[u8]) -> Result<(Chunk, bool), ChunkStoreError> {
+                        match Chunk::decode(pos, data) {
+                            Ok((mut chunk, migrated)) => {
+                                // the stored bytes are outdated until the chunk is written again
+                                chunk.dirty = migrated;
+                                Ok((chunk, migrated))
+                            }
+                            Err(ChunkStoreError::Corrupted { source, .. }) => Err(ChunkStoreError::Corrupted {
+                                pos,
+                                path: self.persistence.storage.path(pos),
+                                source,
+                            }),
+                            Err(e) => Err(e),
+                        }
+                    }
 
-            #[derive(Default, Serialize, Deserialize)]
-            struct Subchunk {
-                sections: [Option<Section<CHUNK_WIDTH, CHUNK_HEIGHT, SUBCHUNK_DEPTH>>; SectionField::COUNT],
-            }
+                    /// Decodes the result of reading a chunk from storage and applies the corruption policy to it.
+                    fn resolve_stored(
+                        &self,
+                        pos: ChunkPosition,
+                        read: io::Result<Option<Vec<u8>>>,
+                    ) -> Result<StoredChunk<Chunk>, ChunkStoreError> {
+                        let stored: Result<(Chunk, bool), ChunkStoreError> = match read {
+                            Ok(Some(encoded_data)) => self.decode_stored(pos, &encoded_data),
+                            Ok(None) => return Ok(StoredChunk::Missing),
+                            Err(error) => Err(self.read_error(pos, error)),
+                        };
 
-            impl Subchunk {
-                // getters
+                        match stored {
+                            Ok((chunk, migrated)) => Ok(StoredChunk::Decoded(chunk, migrated)),
+                            Err(error @ ChunkStoreError::Corrupted { .. }) => match self.persistence.corruption_policy {
+                                CorruptionPolicy::Fail => Err(error),
+                                CorruptionPolicy::Quarantine => Ok(StoredChunk::Quarantine(error)),
+                                CorruptionPolicy::Regenerate => Ok(StoredChunk::Regenerate),
+                            },
+                            Err(error) => Err(error),
+                        }
+                    }
+
+                    /// Claims the passed position for a load, or returns `None` if the chunk is already loaded.
+                    /// Returns a waiter if another load or save of the chunk is running.
+                    fn begin_load(&self, pos: ChunkPosition) -> Result<Option<InFlightGuard<'_>>, InFlightWaiter> {
+                        // an unload keeps the chunk until its write succeeds, so wait for running saves
+                        if self.is_chunk_at_pos(pos) && self.in_flight.state(pos) != Some(ChunkState::Saving) {
+                            return Ok(None);
+                        }
+
+                        let guard = self.in_flight.begin(pos, ChunkState::Loading)?;
+
+                        // loaded between the check and claiming the position
+                        Ok((!self.is_chunk_at_pos(pos)).then_some(guard))
+                    }
+
+                    /// Adds a chunk read or created by a load. Returns false if one was added meanwhile,
+                    /// which is newer than the stored data and kept instead.
+                    fn insert_loaded(&self, pos: ChunkPosition, chunk: Chunk) -> bool {
+                        match self.chunks.entry(pos) {
+                            Entry::Occupied(_) => false,
+                            Entry::Vacant(entry) => {
+                                self.insert_vacant(entry, chunk);
+                                true
+                            }
+                        }
+                    }
+
+                    /// Marks a chunk created instead of read from storage as dirty, so the next save writes it.
+                    fn created(mut chunk: Chunk) -> Chunk {
+                        chunk.dirty = true;
+                        chunk
+                    }
+
+                    /// Encodes the chunk for a write and clears its dirty flag,
+                    /// so edits made while writing mark it dirty again.
+                    fn encode_for_write(&self, pos: ChunkPosition) -> Result<Vec<u8>, ChunkStoreError> {
+                        let mut chunk = self.peek_chunk_mut(pos).map_err(AccessError::from)?;
+                        let encoded_data: Vec<u8> = chunk.encode(self.persistence.compression)?;
+                        chunk.dirty = false;
+                        // picks up edits made through `chunk_mut`
+                        self.recount(&mut chunk);
+                        Ok(encoded_data)
+                    }
+
+                    /// Marks the chunk dirty again after its write failed.
+                    fn write_failed(&self, pos: ChunkPosition, error: io::Error) -> ChunkStoreError {
+                        if let Some(mut chunk) = self.chunks.get_mut(&pos) {
+                            chunk.dirty = true;
+                        }
+                        error.into()
+                    }
+
+                    /// Removes the chunk if storage holds its latest data. Returns false if it is dirty.
+                    fn remove_if_clean(&self, pos: ChunkPosition) -> bool {
+                        match self.chunks.remove_if(&pos, |_, chunk| !chunk.is_dirty()) {
+                            Some((_, chunk)) => {
+                                self.cache.resize(chunk.counted_size, 0);
+                                true
+                            }
+                            None => false,
+                        }
+                    }
+
+                    fn decode_metadata(encoded_data: Option<Vec<u8>>) -> Result<WorldMetadata, ChunkStoreError> {
+                        let encoded_data: Vec<u8> = encoded_data.ok_or(io::Error::from(io::ErrorKind::NotFound))?;
+                        let (metadata, _): (WorldMetadata, usize) = bincode_serde::decode_from_slice(
+                            &encoded_data,
+                            config::standard()
+                        )?;
+                        Ok(metadata)
+                    }
+
+                    #[inline]
+                    fn metadata_saved(&self) -> bool {
+                        self.persistence.metadata_saved.load(Ordering::Acquire)
+                    }
 
-                $(
                     #[inline]
-                    fn $field_name_method(&self, pos: BlockPosition) -> Result<$field_type, BoundsError> {
-                        // Ok(self.item(SectionField::$field_name_enum, pos)? as $field_type)
+                    fn mark_metadata_saved(&self) {
+                        self.persistence.metadata_saved.store(true, Ordering::Release);
+                    }
+                }
+
+                // blocking persistence, for callers without a tokio runtime.
+                // These block the calling thread, so async code should use the async versions instead.
+
+                impl World {
+                    /// Blocking version of [`World::save_metadata`].
+                    pub fn save_metadata_blocking(&self) -> Result<(), ChunkStoreError> {
+                        let encoded_data = encode_to_vec(Self::metadata(), config::standard())?;
+                        self.persistence.storage.write_metadata_blocking(encoded_data)?;
+                        self.mark_metadata_saved();
+                        Ok(())
+                    }
+
+                    /// Blocking version of [`World::load_metadata`].
+                    pub fn load_metadata_blocking(&self) -> Result<WorldMetadata, ChunkStoreError> {
+                        Self::decode_metadata(self.persistence.storage.read_metadata_blocking()?)
+                    }
+
+                    /// Blocking version of [`World::stored_chunks`].
+                    pub fn stored_chunks_blocking(&self) -> Result<Vec<ChunkPosition>, ChunkStoreError> {
+                        Ok(self.persistence.storage.list_blocking()?)
+                    }
+
+                    fn prepare_storage_blocking(&self) -> Result<(), ChunkStoreError> {
+                        if self.metadata_saved() {
+                            return Ok(());
+                        }
+
+                        let _init = self.persistence.metadata_init.lock().unwrap();
+
+                        // written by another save while waiting for the lock
+                        if self.metadata_saved() {
+                            return Ok(());
+                        }
+
+                        if self.persistence.storage.read_metadata_blocking()?.is_none() {
+                            return self.save_metadata_blocking();
+                        }
+
+                        self.mark_metadata_saved();
+                        Ok(())
+                    }
+
+                    /// Blocking version of [`World::save_chunk`].
+                    pub fn save_chunk_blocking(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        let _guard = self.in_flight.claim_blocking(pos, ChunkState::Saving);
+                        self.write_chunk_blocking(pos)
+                    }
+
+                    /// Writes the chunk to storage, the caller holds the save claim.
+                    fn write_chunk_blocking(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        self.prepare_storage_blocking()?;
+
+                        let encoded_data: Vec<u8> = self.encode_for_write(pos)?;
+                        self.persistence.storage
+                            .write_blocking(pos, encoded_data)
+                            .map_err(|e| self.write_failed(pos, e))
+                    }
+
+                    /// Blocking version of [`World::save_all_dirty`].
+                    pub fn save_all_dirty_blocking(&self) -> Result<usize, ChunkStoreError> {
+                        let mut written: usize = 0;
 
-                        Ok(
-                            <$field_type as FieldType>::from_u64(
-                                self.item(SectionField::$field_name_enum, pos)?
-                            )
+                        for pos in self.dirty_chunks() {
+                            if Self::unless_unloaded(self.save_chunk_blocking(pos))? {
+                                written += 1;
+                            }
+                        }
+
+                        Ok(written)
+                    }
+
+                    /// Blocking version of [`World::unload_chunk`].
+                    pub fn unload_chunk_blocking(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        let _guard = self.in_flight.claim_blocking(pos, ChunkState::Saving);
+
+                        // edited while being written, so write again until storage holds the latest data
+                        while !self.remove_if_clean(pos) {
+                            self.write_chunk_blocking(pos)?;
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Blocking version of [`World::save_all`], writing one chunk at a time.
+                    pub fn save_all_blocking(&self) -> Result<usize, SaveAllError> {
+                        Self::collect_failures(
+                            self.loaded_chunks().into_iter().map(|pos| (pos, self.save_chunk_blocking(pos)))
                         )
                     }
-                )*
 
-                // setters
+                    /// Blocking version of [`World::shutdown`], unloading one chunk at a time.
+                    pub fn shutdown_blocking(&self) -> Result<usize, SaveAllError> {
+                        Self::collect_failures(
+                            self.loaded_chunks().into_iter().map(|pos| (pos, self.unload_chunk_blocking(pos)))
+                        )
+                    }
 
-                paste! {
-                    $(
-                        #[must_use]
-                        #[inline]
-                        fn [<set_ $field_name_method>](
-                            &mut self,
-                            pos: BlockPosition,
-                            value: $field_type
-                        ) -> Result<(), BoundsError> {
-                            self.set_item(
-                                SectionField::$field_name_enum, pos, <$field_type as FieldType>::to_u64(value)
-                            )?;
-                            Ok(())
+                    /// Blocking version of [`World::evict`].
+                    pub fn evict_blocking(&self) -> Result<Vec<ChunkPosition>, ChunkStoreError> {
+                        Self::collect_evicted(
+                            self.eviction_victims()
+                                .into_iter()
+                                // pinned since the victims were chosen, checked right before each unload
+                                .filter(|&pos| !self.cache.is_pinned(pos))
+                                .map(|pos| (pos, self.unload_chunk_blocking(pos)))
+                        )
+                    }
+
+                    /// Blocking version of [`World::load_chunk`].
+                    pub fn load_chunk_blocking(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        self.load_with_blocking(pos, None::<fn() -> Result<Chunk, ChunkStoreError>>)?;
+                        Ok(())
+                    }
+
+                    /// Blocking version of [`World::load_or_else`].
+                    pub fn load_or_else_blocking<F>(&self, pos: ChunkPosition, create: F) -> Result<(), ChunkStoreError>
+                    where
+                        F: FnOnce() -> Chunk,
+                    {
+                        self.load_with_blocking(pos, Some(move || Ok(create())))?;
+                        Ok(())
+                    }
+
+                    /// Blocking version of [`World::load_or_default`].
+                    pub fn load_or_default_blocking(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        self.load_or_else_blocking(pos, Chunk::default)
+                    }
+
+                    /// Blocking version of [`World::get_or_generate`], generating on the calling thread.
+                    pub fn get_or_generate_blocking(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        let generator: &dyn ChunkGenerator<Chunk> =
+                            self.generator.as_deref().ok_or(ChunkStoreError::NoGenerator)?;
+
+                        self.load_with_blocking(pos, Some(|| Ok(Self::generate_chunk(generator, pos)?)))?;
+                        Ok(())
+                    }
+
+                    /// Blocking version of [`World::load_with`].
+                    fn load_with_blocking<F>(&self, pos: ChunkPosition, create: Option<F>) -> Result<bool, ChunkStoreError>
+                    where
+                        F: FnOnce() -> Result<Chunk, ChunkStoreError>,
+                    {
+                        let has_fallback: bool = create.is_some();
+                        let already_loaded = || if has_fallback {
+                            Ok(false)
+                        } else {
+                            Err(ChunkStoreError::ChunkOverwrite(ChunkOverwriteError::ChunkAlreadyLoaded(pos)))
+                        };
+
+                        let guard = loop {
+                            match self.begin_load(pos) {
+                                Ok(Some(guard)) => break guard,
+                                Ok(None) => return already_loaded(),
+                                Err(waiter) => {
+                                    let joined: bool = waiter.state() == ChunkState::Loading;
+                                    waiter.wait_blocking();
+
+                                    if joined && self.is_chunk_at_pos(pos) {
+                                        return Ok(false);
+                                    }
+                                }
+                            }
+                        };
+
+                        let (chunk, migrated): (Chunk, bool) = match self.resolve_stored(pos, self.persistence.storage.read_blocking(pos))? {
+                            StoredChunk::Decoded(chunk, migrated) => (chunk, migrated),
+                            StoredChunk::Missing => {
+                                let Some(create) = create else {
+                                    return Err(ChunkStoreError::NotStored(pos));
+                                };
+
+                                return Ok(self.insert_loaded(pos, Self::created(create()?)));
+                            }
+                            StoredChunk::Quarantine(error) => {
+                                self.persistence.storage.quarantine_blocking(pos)?;
+                                return Err(error);
+                            }
+                            StoredChunk::Regenerate => {
+                                let chunk: Chunk = match self.generator.as_deref() {
+                                    Some(generator) => Self::generate_chunk(generator, pos)?,
+                                    None => Chunk::default(),
+                                };
+                                (Self::created(chunk), false)
+                            }
+                        };
+
+                        if !self.insert_loaded(pos, chunk) {
+                            return already_loaded();
                         }
-                    )*
+
+                        drop(guard);
+
+                        if migrated && self.persistence.rewrite_migrated {
+                            self.save_chunk_blocking(pos)?;
+                        }
+
+                        Ok(true)
+                    }
                 }
+            }
 
-                #[inline]
-                fn item(&self, section_field: SectionField, pos: BlockPosition) -> Result<u64, BoundsError> {
-                    self.sections[section_field as usize].as_ref().map_or(Ok(0), |s| s.item(pos))
+            $crate::__cfg_async_io! {
+                impl World {
+                    /// Writes the metadata of this world definition to storage.
+                    pub async fn save_metadata(&self) -> Result<(), ChunkStoreError> {
+                        let encoded_data = encode_to_vec(Self::metadata(), config::standard())?;
+                        self.persistence.storage.write_metadata(encoded_data).await?;
+                        self.mark_metadata_saved();
+                        Ok(())
+                    }
+
+                    /// Reads the metadata previously saved to storage.
+                    pub async fn load_metadata(&self) -> Result<WorldMetadata, ChunkStoreError> {
+                        Self::decode_metadata(self.persistence.storage.read_metadata().await?)
+                    }
+
+                    /// Returns the positions of every chunk saved in storage.
+                    pub async fn stored_chunks(&self) -> Result<Vec<ChunkPosition>, ChunkStoreError> {
+                        Ok(self.persistence.storage.list().await?)
+                    }
+
+                    /// Writes the metadata to storage once if it is missing there.
+                    async fn prepare_storage(&self) -> Result<(), ChunkStoreError> {
+                        if self.metadata_saved() {
+                            return Ok(());
+                        }
+
+                        let _init = self.persistence.metadata_init_async.lock().await;
+
+                        // written by another save while waiting for the lock
+                        if self.metadata_saved() {
+                            return Ok(());
+                        }
+
+                        if self.persistence.storage.read_metadata().await?.is_none() {
+                            return self.save_metadata().await;
+                        }
+
+                        self.mark_metadata_saved();
+                        Ok(())
+                    }
+
+                    /// Unloads the least recently used unpinned chunks until the world fits its memory budget,
+                    /// writing dirty ones to storage. Returns the positions of the evicted chunks.
+                    /// All chosen chunks are attempted even if some fail, and the first error is returned.
+                    pub async fn evict(&self) -> Result<Vec<ChunkPosition>, ChunkStoreError> {
+                        let mut results: Vec<(ChunkPosition, Result<(), ChunkStoreError>)> = Vec::new();
+
+                        for pos in self.eviction_victims() {
+                            // pinned since the victims were chosen
+                            if !self.cache.is_pinned(pos) {
+                                results.push((pos, self.unload_chunk(pos).await));
+                            }
+                        }
+
+                        Self::collect_evicted(results)
+                    }
+
+                    /// Spawns a tokio task that evicts chunks whenever adding or loading one puts the world over its budget,
+                    /// or under a byte budget, whenever a setter of the world grows a chunk over it.
+                    /// Chunks failing to save stay loaded. The task ends once the world is dropped.
+                    pub fn spawn_evictor(self: &Arc<Self>) -> task::JoinHandle<()> {
+                        let world: Weak<Self> = Arc::downgrade(self);
+                        let cache: Arc<ChunkCache> = Arc::clone(&self.cache);
+
+                        task::spawn(async move {
+                            loop {
+                                cache.notified().await;
+
+                                let Some(world) = world.upgrade() else {
+                                    return;
+                                };
+
+                                let _ = world.evict().await;
+                            }
+                        })
+                    }
+
+                    /// Writes the chunk at the passed position to storage and keeps it loaded.
+                    /// Clears its dirty flag unless the write fails.
+                    /// Waits for any other load or save of the chunk to finish first.
+                    pub async fn save_chunk(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        let _guard = self.in_flight.claim(pos, ChunkState::Saving).await;
+                        self.write_chunk(pos).await
+                    }
+
+                    /// Writes the chunk to storage, the caller holds the save claim.
+                    async fn write_chunk(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        self.prepare_storage().await?;
+
+                        let encoded_data: Vec<u8> = self.encode_for_write(pos)?;
+                        self.persistence.storage
+                            .write(pos, encoded_data)
+                            .await
+                            .map_err(|e| self.write_failed(pos, e))
+                    }
+
+                    /// Saves every dirty chunk without unloading it.
+                    /// Returns the number of chunks written.
+                    pub async fn save_all_dirty(&self) -> Result<usize, ChunkStoreError> {
+                        let mut written: usize = 0;
+
+                        for pos in self.dirty_chunks() {
+                            if Self::unless_unloaded(self.save_chunk(pos).await)? {
+                                written += 1;
+                            }
+                        }
+
+                        Ok(written)
+                    }
+
+                    /// Removes the chunk at the passed position, writing it to storage if it is dirty.
+                    /// The chunk stays loaded until the write succeeds, so a failed unload loses no data.
+                    /// Loads of the chunk started meanwhile wait for the write and read the new data.
+                    pub async fn unload_chunk(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        let _guard = self.in_flight.claim(pos, ChunkState::Saving).await;
+
+                        // edited while being written, so write again until storage holds the latest data
+                        while !self.remove_if_clean(pos) {
+                            self.write_chunk(pos).await?;
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Writes every loaded chunk to storage and keeps it loaded, running up to `io_concurrency` writes at once.
+                    /// All chunks are attempted, the ones that failed stay dirty and are listed in the error.
+                    /// Returns the number of chunks written.
+                    pub async fn save_all(self: &Arc<Self>) -> Result<usize, SaveAllError> {
+                        self.for_each_loaded(|world, pos| async move { world.save_chunk(pos).await }).await
+                    }
+
+                    /// Saves and removes every loaded chunk, running up to `io_concurrency` at once.
+                    /// All chunks are attempted, the ones that failed stay loaded and are listed in the error,
+                    /// so the shutdown can be retried. Returns the number of chunks unloaded.
+                    pub async fn shutdown(self: &Arc<Self>) -> Result<usize, SaveAllError> {
+                        self.for_each_loaded(|world, pos| async move { world.unload_chunk(pos).await }).await
+                    }
+
+                    async fn for_each_loaded<F, Fut>(self: &Arc<Self>, operation: F) -> Result<usize, SaveAllError>
+                    where
+                        F: Fn(Arc<Self>, ChunkPosition) -> Fut,
+                        Fut: Future<Output = Result<(), ChunkStoreError>> + Send + 'static,
+                    {
+                        Self::collect_failures(run_limited(self, self.persistence.io_concurrency, self.loaded_chunks(), operation).await)
+                    }
+
+                    /// Loads the chunk at the passed position from storage.
+                    /// Chunks saved with an older schema version are migrated and marked dirty.
+                    /// Corrupted chunks are handled according to the world's [`CorruptionPolicy`].
+                    ///
+                    /// Concurrent loads of the same position share a single read and all succeed once it does.
+                    /// A load started during an unload waits for its write to finish.
+                    ///
+                    /// Returns [`ChunkStoreError::NotStored`] if the chunk was never saved.
+                    pub async fn load_chunk(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        self.load_with(pos, None::<fn() -> Ready<Result<Chunk, ChunkStoreError>>>).await?;
+                        Ok(())
+                    }
+
+                    /// Loads the chunk at the passed position if it was saved, otherwise adds the chunk returned by `create`.
+                    /// Does nothing if the chunk is already loaded.
+                    pub async fn load_or_else<F>(&self, pos: ChunkPosition, create: F) -> Result<(), ChunkStoreError>
+                    where
+                        F: FnOnce() -> Chunk + Send,
+                    {
+                        self.load_with(pos, Some(move || future::ready(Ok(create())))).await?;
+                        Ok(())
+                    }
+
+                    /// Loads the chunk at the passed position if it was saved, otherwise adds an empty chunk.
+                    pub async fn load_or_default(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        self.load_or_else(pos, Chunk::default).await
+                    }
+
+                    /// Loads the chunk at the passed position if it was saved, otherwise creates it with the world's generator.
+                    /// Generation runs on tokio's blocking thread pool. Does nothing if the chunk is already loaded.
+                    pub async fn get_or_generate(&self, pos: ChunkPosition) -> Result<(), ChunkStoreError> {
+                        let generator: Arc<dyn ChunkGenerator<Chunk>> =
+                            self.generator.clone().ok_or(ChunkStoreError::NoGenerator)?;
+
+                        self.load_with(pos, Some(move || Self::run_generator(generator, pos))).await?;
+                        Ok(())
+                    }
+
+                    /// Loads or generates every passed chunk, running them in parallel as tokio tasks.
+                    /// All positions are attempted even if some fail, and the first error is returned.
+                    pub async fn get_or_generate_all(
+                        self: &Arc<Self>,
+                        positions: impl IntoIterator<Item = ChunkPosition>,
+                    ) -> Result<(), ChunkStoreError> {
+                        self.for_each_parallel(positions, |world, pos| async move { world.get_or_generate(pos).await })
+                            .await
+                    }
+
+                    async fn run_generator(
+                        generator: Arc<dyn ChunkGenerator<Chunk>>,
+                        pos: ChunkPosition,
+                    ) -> Result<Chunk, ChunkStoreError> {
+                        match task::spawn_blocking(move || Self::generate_chunk(&*generator, pos)).await {
+                            Ok(chunk) => Ok(chunk?),
+                            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                            Err(e) => Err(io::Error::other(e).into()),
+                        }
+                    }
+
+                    /// Runs the generation stages until the chunk at the passed position has completed `stage` of them.
+                    ///
+                    /// A chunk only runs stage N once all chunks within that stage's radius have completed stage N - 1,
+                    /// so surrounding chunks are loaded or created empty and partially generated as needed.
+                    /// The world's generator is not used for them.
+                    /// Chunks at the same stage are generated in parallel, unless their radii overlap.
+                    /// Those run one at a time in no particular order, see [`GenerationStage`].
+                    pub async fn generate_to(self: &Arc<Self>, pos: ChunkPosition, stage: u8) -> Result<(), ChunkStoreError> {
+                        let target: usize = (stage as usize).min(self.stages.len());
+                        let widest: u32 = self.stages[..target].iter().map(|stage| stage.radius()).sum();
+                        // keeps the evictor from dropping chunks between stages or while a stage writes into them
+                        let _pins: Vec<PinGuard> =
+                            Self::positions_in_square(pos, widest).map(|pos| self.cache.pin_guard(pos)).collect();
+
+                        for index in 0..target {
+                            // chunks the later stages touch need this stage too
+                            let reach: u32 = self.stages[index + 1..target].iter().map(|stage| stage.radius()).sum();
+                            let radius: u32 = self.stages[index].radius();
+
+                            self.for_each_parallel(Self::positions_in_square(pos, reach + radius), |world, pos| async move {
+                                world.load_or_default(pos).await
+                            })
+                            .await?;
+
+                            self.for_each_parallel(Self::positions_in_square(pos, reach), move |world, pos| async move {
+                                world.run_stage(pos, index).await
+                            })
+                            .await?;
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Runs the stage with the passed index on a loaded chunk, unless it already completed it.
+                    async fn run_stage(self: &Arc<Self>, pos: ChunkPosition, index: usize) -> Result<(), ChunkStoreError> {
+                        let mut guards: Vec<InFlightGuard> = Vec::new();
+
+                        // stages whose squares overlap run one at a time, every square is claimed
+                        // in the same order so they cannot deadlock
+                        for pos in Self::positions_in_square(pos, self.stages[index].radius()) {
+                            guards.push(self.in_flight.claim(pos, ChunkState::Generating).await);
+                        }
+
+                        if self.peek_chunk(pos).map_err(AccessError::from)?.generation_stage as usize > index {
+                            return Ok(());
+                        }
+
+                        let stage: Arc<dyn GenerationStage<World>> = Arc::clone(&self.stages[index]);
+                        let world: Arc<Self> = Arc::clone(self);
+
+                        match task::spawn_blocking(move || stage.apply(&world, pos)).await {
+                            Ok(result) => result?,
+                            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                            Err(e) => return Err(io::Error::other(e).into()),
+                        }
+
+                        let mut chunk = self.peek_chunk_mut(pos).map_err(AccessError::from)?;
+                        chunk.generation_stage = index as u8 + 1;
+                        chunk.dirty = true;
+
+                        Ok(())
+                    }
+
+                    /// Runs the passed operation for every position as parallel tokio tasks.
+                    /// All positions are attempted even if some fail, and the first error is returned.
+                    async fn for_each_parallel<F, Fut>(
+                        self: &Arc<Self>,
+                        positions: impl IntoIterator<Item = ChunkPosition>,
+                        operation: F,
+                    ) -> Result<(), ChunkStoreError>
+                    where
+                        F: Fn(Arc<Self>, ChunkPosition) -> Fut,
+                        Fut: Future<Output = Result<(), ChunkStoreError>> + Send + 'static,
+                    {
+                        let mut tasks: JoinSet<Result<(), ChunkStoreError>> = JoinSet::new();
+
+                        for pos in positions {
+                            tasks.spawn(operation(Arc::clone(self), pos));
+                        }
+
+                        let mut result: Result<(), ChunkStoreError> = Ok(());
+
+                        while let Some(joined) = tasks.join_next().await {
+                            let outcome: Result<(), ChunkStoreError> = match joined {
+                                Ok(outcome) => outcome,
+                                Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                                Err(e) => Err(io::Error::other(e).into()),
+                            };
+
+                            if result.is_ok() {
+                                result = outcome;
+                            }
+                        }
+
+                        result
+                    }
+
+                    /// Loads a chunk, falling back to `create` if one is passed and the chunk was never saved.
+                    /// A loaded chunk is only an error without a fallback.
+                    /// Returns true if this call added the chunk, false if it was loaded by someone else.
+                    async fn load_with<F, Fut>(&self, pos: ChunkPosition, create: Option<F>) -> Result<bool, ChunkStoreError>
+                    where
+                        F: FnOnce() -> Fut + Send,
+                        Fut: Future<Output = Result<Chunk, ChunkStoreError>> + Send,
+                    {
+                        let has_fallback: bool = create.is_some();
+                        let already_loaded = || if has_fallback {
+                            Ok(false)
+                        } else {
+                            Err(ChunkStoreError::ChunkOverwrite(ChunkOverwriteError::ChunkAlreadyLoaded(pos)))
+                        };
+
+                        let guard = loop {
+                            match self.begin_load(pos) {
+                                Ok(Some(guard)) => break guard,
+                                Ok(None) => return already_loaded(),
+                                Err(waiter) => {
+                                    let joined: bool = waiter.state() == ChunkState::Loading;
+                                    waiter.wait().await;
+
+                                    if joined && self.is_chunk_at_pos(pos) {
+                                        return Ok(false);
+                                    }
+                                }
+                            }
+                        };
+
+                        let (chunk, migrated): (Chunk, bool) = match self.resolve_stored(pos, self.persistence.storage.read(pos).await)? {
+                            StoredChunk::Decoded(chunk, migrated) => (chunk, migrated),
+                            StoredChunk::Missing => {
+                                let Some(create) = create else {
+                                    return Err(ChunkStoreError::NotStored(pos));
+                                };
+
+                                return Ok(self.insert_loaded(pos, Self::created(create().await?)));
+                            }
+                            StoredChunk::Quarantine(error) => {
+                                self.persistence.storage.quarantine(pos).await?;
+                                return Err(error);
+                            }
+                            StoredChunk::Regenerate => {
+                                let chunk: Chunk = match self.generator.clone() {
+                                    Some(generator) => Self::run_generator(generator, pos).await?,
+                                    None => Chunk::default(),
+                                };
+                                (Self::created(chunk), false)
+                            }
+                        };
+
+                        if !self.insert_loaded(pos, chunk) {
+                            return already_loaded();
+                        }
+
+                        drop(guard);
+
+                        if migrated && self.persistence.rewrite_migrated {
+                            self.save_chunk(pos).await?;
+                        }
+
+                        Ok(true)
+                    }
                 }
 
-                #[must_use]
-                #[inline]
-                fn set_item(
-                    &mut self,
-                    section_field: SectionField,
-                    pos: BlockPosition,
-                    value: u64
-                ) -> Result<(), BoundsError> {
-                    let section_index = section_field as usize;
+                impl ChunkLoader for World {
+                    /// Generates chunks that were never saved if the world has a generator, otherwise adds empty ones.
+                    fn load(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<bool, ChunkStoreError>> {
+                        Box::pin(async move {
+                            match self.generator.clone() {
+                                Some(generator) => self.load_with(pos, Some(move || Self::run_generator(generator, pos))).await,
+                                None => self.load_with(pos, Some(|| future::ready(Ok(Chunk::default())))).await,
+                            }
+                        })
+                    }
 
-                    if value == 0 && self.sections[section_index].is_none() {
-                        return Ok(());
+                    fn unload(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<(), ChunkStoreError>> {
+                        Box::pin(self.unload_chunk(pos))
                     }
+                }
 
-                    let section = self.sections[section_index].get_or_insert_with(||
-                        Section::new(section_field.bits())
-                    );
+                impl ChunkSaver for World {
+                    fn dirty_chunks(&self) -> Vec<ChunkPosition> {
+                        World::dirty_chunks(self)
+                    }
+
+                    fn save(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<(), ChunkStoreError>> {
+                        Box::pin(self.save_chunk(pos))
+                    }
+                }
+            }
+
+            // -- WorldBuilder --
 
-                    section.set_item(pos, value)?;
+            /// Configures and creates a [`World`].
+            #[derive(Default)]
+            pub struct WorldBuilder {
+                persistence: PersistenceOptions,
+                generator: Option<Arc<dyn ChunkGenerator<Chunk>>>,
+                stages: Vec<Arc<dyn GenerationStage<World>>>,
+                memory_budget: MemoryBudget,
+            }
+
+            impl WorldBuilder {
+                /// Sets the generator creating chunks that were never saved.
+                pub fn generator(mut self, generator: impl ChunkGenerator<Chunk> + 'static) -> Self {
+                    self.generator = Some(Arc::new(generator));
+                    self
+                }
+
+                /// Appends a stage to the generation pipeline run by `World::generate_to`.
+                pub fn stage(mut self, stage: impl GenerationStage<World> + 'static) -> Self {
+                    self.stages.push(Arc::new(stage));
+                    self
+                }
+
+                /// Sets how much the world keeps loaded before evicting the least recently used chunks.
+                /// Eviction runs through `World::evict` or the task started by `World::spawn_evictor`.
+                pub fn memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+                    self.memory_budget = memory_budget;
+                    self
+                }
+
+                /// Builds the world.
+                pub fn build(self) -> World {
+                    World {
+                        chunks: DashMap::default(),
+                        in_flight: InFlight::new(),
+                        persistence: self.persistence.build(),
+                        generator: self.generator,
+                        stages: self.stages,
+                        cache: Arc::new(ChunkCache::new(self.memory_budget)),
+                    }
+                }
+            }
+
+            $crate::__cfg_persistence! {
+                impl WorldBuilder {
+                    /// Sets the directory the built-in file storages save to.
+                    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+                        self.persistence.root = root.into();
+                        self
+                    }
+
+                    /// Sets the layout the built-in file storages save in.
+                    pub fn format(mut self, format: StorageFormat) -> Self {
+                        self.persistence.format = format;
+                        self
+                    }
+
+                    /// Sets a custom storage backend, overriding `root` and `format`.
+                    pub fn storage(mut self, storage: impl ChunkStorage + 'static) -> Self {
+                        self.persistence.storage = Some(Arc::new(storage));
+                        self
+                    }
+
+                    /// Sets whether chunks migrated from an older schema are written back right after loading.
+                    /// Otherwise they are only marked dirty and rewritten on the next save.
+                    pub fn rewrite_migrated(mut self, rewrite_migrated: bool) -> Self {
+                        self.persistence.rewrite_migrated = rewrite_migrated;
+                        self
+                    }
+
+                    /// Sets the codec chunks are compressed with when saved.
+                    pub fn compression(mut self, compression: Compression) -> Self {
+                        self.persistence.compression = compression;
+                        self
+                    }
+
+                    /// Sets what loading does when a stored chunk is found to be corrupted.
+                    pub fn corruption_policy(mut self, corruption_policy: CorruptionPolicy) -> Self {
+                        self.persistence.corruption_policy = corruption_policy;
+                        self
+                    }
+
+                    /// Sets how many chunks `World::save_all` and `World::shutdown` write at once. Defaults to 16.
+                    pub fn io_concurrency(mut self, io_concurrency: usize) -> Self {
+                        self.persistence.io_concurrency = io_concurrency.max(1);
+                        self
+                    }
+                }
+            }
+
+            // -- Chunk --
+
+            $crate::__derive_serde! {
+                #[derive(Default)]
+                pub struct Chunk {
+                    subchunks: [Option<Subchunk>; NUM_SUBCHUNKS],
+                    #[serde(skip)]
+                    dirty: bool,
+                    #[serde(default)]
+                    generation_stage: u8,
+                    #[serde(skip)]
+                    last_access: AtomicU64,
+                    // size last added to the tracked bytes of the world, see `World::recount`
+                    #[serde(skip)]
+                    counted_size: usize,
+                }
+            }
+
+            impl ChunkShape for Chunk {
+                const WIDTH: usize = CHUNK_WIDTH;
+                const HEIGHT: usize = CHUNK_HEIGHT;
+                const DEPTH: usize = CHUNK_DEPTH;
+                const MIN_Z: i32 = MIN_Z;
+            }
+
+            impl Chunk {
+                /// Returns true if the chunk was modified since it was last saved or loaded.
+                #[inline]
+                pub fn is_dirty(&self) -> bool {
+                    self.dirty
+                }
+
+                /// Returns an estimate of the bytes the chunk takes up in memory.
+                pub fn memory_size(&self) -> usize {
+                    const SUBCHUNK_VOLUME: usize = CHUNK_WIDTH * CHUNK_HEIGHT * SUBCHUNK_DEPTH;
+
+                    self.subchunks
+                        .iter()
+                        .flatten()
+                        .map(|subchunk| {
+                            let sections: usize = subchunk.sections
+                                .iter()
+                                .zip(SectionField::BITS_PER_ITEM_TABLE)
+                                .filter(|(section, _)| section.is_some())
+                                .map(|(_, &bits)| (SUBCHUNK_VOLUME * bits as usize).div_ceil(64) * 8)
+                                .sum();
+                            mem::size_of::<Subchunk>() + sections
+                        })
+                        .sum::<usize>()
+                        + mem::size_of::<Chunk>()
+                }
+
+                /// Returns the number of generation stages the chunk completed,
+                /// or [`FULLY_GENERATED`] if it came from a [`ChunkGenerator`].
+                #[inline]
+                pub fn generation_stage(&self) -> u8 {
+                    self.generation_stage
+                }
+
+                // getters
+
+                $(
+                    #[inline]
+                    pub fn $field_name_method(&self, pos: BlockPosition) -> Result<$field_type, AccessError> {
+                        Self::check_bounds(pos)?;
+                        let subchunk_opt: &Option<Subchunk> = &self.subchunks[Self::subchunk_index(pos.z)];
+
+                        subchunk_opt.as_ref().map_or_else(|| SectionField::$field_name_enum.decode(SectionField::$field_name_enum.default_raw()), |s| {
+                            let sub_pos: BlockPosition = Self::local_to_sub(pos);
+                            s.$field_name_method(sub_pos)
+                        })
+                    }
+                )*
+
+                // setters
+
+                paste! {
+                    $(
+                        #[inline]
+                        pub fn [<set_ $field_name_method>](
+                            &mut self,
+                            pos: BlockPosition,
+                            value: $field_type
+                        ) -> Result<(), AccessError> {
+                            Self::check_bounds(pos)?;
+                            let raw: u64 = <$field_type as FieldType>::to_u64(value);
+                            SectionField::$field_name_enum.check_value(raw)?;
+                            let subchunk_opt: &mut Option<Subchunk> = &mut self.subchunks[Self::subchunk_index(pos.z)];
+
+                            if raw == SectionField::$field_name_enum.default_raw() && subchunk_opt.is_none() {
+                                return Ok(()); // return if placement is redundant
+                            }
+
+                            let subchunk: &mut Subchunk = subchunk_opt.get_or_insert_with(|| Subchunk::default());
+                            let sub_pos: BlockPosition = Self::local_to_sub(pos);
+
+                            subchunk.[<set_ $field_name_method>](sub_pos, value)?;
+
+                            if subchunk.is_empty() {
+                                *subchunk_opt = None; // set empty subchunks to none
+                            }
+
+                            self.dirty = true;
+
+                            Ok(())
+                        }
+                    )*
+                }
+
+                /// Returns true if the passed local position lies within the chunk.
+                #[inline]
+                pub const fn contains(pos: BlockPosition) -> bool {
+                    pos.x >= 0 && pos.x < CHUNK_WIDTH as i32
+                        && pos.y >= 0 && pos.y < CHUNK_HEIGHT as i32
+                        && pos.z >= MIN_Z && pos.z < MAX_Z
+                }
+
+                #[inline]
+                fn check_bounds(pos: BlockPosition) -> Result<(), BoundsError> {
+                    if Self::contains(pos) {
+                        Ok(())
+                    } else {
+                        Err(BoundsError::OutOfBounds(pos))
+                    }
+                }
+
+                /// Returns the index of the subchunk holding the passed z, which must be within the chunk.
+                #[inline]
+                const fn subchunk_index(pos_z: i32) -> usize {
+                    (pos_z - MIN_Z) as usize / SUBCHUNK_DEPTH
+                }
+
+                #[inline]
+                const fn local_to_sub(pos: BlockPosition) -> BlockPosition {
+                    BlockPosition::new(pos.x, pos.y, (pos.z - MIN_Z) % SUBCHUNK_DEPTH as i32)
+                }
+            }
+
+            $crate::__cfg_persistence! {
+                impl Chunk {
+                    /// Encodes the chunk with a header describing the world schema.
+                    /// Sections are stored as lists indexed by the header's fields so older data stays decodable.
+                    fn encode(&self, compression: Compression) -> Result<Vec<u8>, ChunkStoreError> {
+                        let header: ChunkHeader = ChunkHeader {
+                            schema: SCHEMA,
+                            schema_version: SCHEMA_VERSION,
+                            fields: SectionField::infos(),
+                            compression,
+                            generation_stage: self.generation_stage,
+                        };
+
+                        let subchunks: Vec<Option<&[Option<Section<CHUNK_WIDTH, CHUNK_HEIGHT, SUBCHUNK_DEPTH>>]>> =
+                            self.subchunks
+                                .iter()
+                                .map(|subchunk| subchunk.as_ref().map(|s| &s.sections[..]))
+                                .collect();
+
+                        let body: Vec<u8> = compression.compress(&encode_to_vec(&subchunks, config::standard())?)?;
+                        Ok(format::encode_chunk_file(&header, &body)?)
+                    }
+
+                    /// Decodes a chunk stored at the passed position, migrating it from older schema versions.
+                    /// Returns true alongside the chunk if a migration was applied.
+                    fn decode(pos: ChunkPosition, data: &[u8]) -> Result<(Self, bool), ChunkStoreError> {
+                        let corrupted = |source: CorruptionError| ChunkStoreError::Corrupted { pos, path: None, source };
+
+                        let (header, body) = format::decode_chunk_file(data).map_err(|source| match source {
+                            // foreign data or written by another version of the crate,
+                            // which no corruption policy may discard
+                            HeaderError::BadMagic | HeaderError::LegacyFormat | HeaderError::UnsupportedVersion(_) => {
+                                ChunkStoreError::InvalidHeader { pos, source }
+                            }
+                            source => corrupted(source.into()),
+                        })?;
+
+                        let mismatch = || ChunkStoreError::SchemaMismatch {
+                            pos,
+                            expected: SCHEMA,
+                            found: header.schema,
+                        };
+
+                        let migrated: bool = header.schema_version != SCHEMA_VERSION;
+
+                        if !header.schema.same_layout(&SCHEMA) {
+                            return Err(mismatch());
+                        }
+
+                        if !migrated && header.schema.fingerprint != SCHEMA.fingerprint {
+                            return Err(mismatch());
+                        }
+
+                        let body: Vec<u8> = header.compression.decompress(body).map_err(|source| match source {
+                            CompressionError::Unsupported(_) => ChunkStoreError::Compression(source),
+                            source => corrupted(source.into()),
+                        })?;
+                        let (mut subchunks, _): (Vec<SubchunkRecord<CHUNK_WIDTH, CHUNK_HEIGHT, SUBCHUNK_DEPTH>>, usize) =
+                            bincode_serde::decode_from_slice(&body, config::standard())
+                                .map_err(|source| corrupted(source.into()))?;
+
+                        if subchunks.len() != NUM_SUBCHUNKS {
+                            return Err(corrupted(CorruptionError::SubchunkCount {
+                                expected: NUM_SUBCHUNKS,
+                                found: subchunks.len(),
+                            }));
+                        }
+
+                        if let Some(sections) = subchunks.iter().flatten().find(|sections| sections.len() != header.fields.len()) {
+                            return Err(corrupted(CorruptionError::SectionCount {
+                                expected: header.fields.len(),
+                                found: sections.len(),
+                            }));
+                        }
+
+                        if let Some(duplicate) = header.fields.iter().enumerate().find_map(|(index, field)| {
+                            header.fields[..index].iter().any(|other| other.name == field.name).then_some(field)
+                        }) {
+                            return Err(corrupted(CorruptionError::DuplicateField(duplicate.name.clone())));
+                        }
+
+                        let mut fields: Vec<FieldInfo> = header.fields.clone();
+
+                        if migrated {
+                            migration::migrate(&mut fields, &mut subchunks, header.schema_version, SCHEMA_VERSION, MIGRATIONS)
+                                .map_err(|source| ChunkStoreError::Migration { pos, source })?;
+                        }
+
+                        migration::arrange(&fields, &mut subchunks, &SectionField::infos())
+                            .map_err(|source| ChunkStoreError::Migration { pos, source })?;
+
+                        let mut chunk: Chunk = Chunk {
+                            generation_stage: header.generation_stage,
+                            ..Chunk::default()
+                        };
+
+                        for (slot, record) in chunk.subchunks.iter_mut().zip(subchunks) {
+                            *slot = match record {
+                                Some(sections) => Some(Subchunk {
+                                    sections: sections.try_into().map_err(|_| mismatch())?,
+                                }),
+                                None => None,
+                            };
+                        }
+
+                        Ok((chunk, migrated))
+                    }
+                }
+            }
+
+            // -- Subchunk --
+
+            $crate::__derive_serde! {
+                #[derive(Default)]
+                struct Subchunk {
+                    sections: [Option<Section<CHUNK_WIDTH, CHUNK_HEIGHT, SUBCHUNK_DEPTH>>; SectionField::COUNT],
+                }
+            }
+
+            impl Subchunk {
+                // getters
+
+                $(
+                    #[inline]
+                    fn $field_name_method(&self, pos: BlockPosition) -> Result<$field_type, AccessError> {
+                        SectionField::$field_name_enum.decode(self.item(SectionField::$field_name_enum, pos)?)
+                    }
+                )*
+
+                // setters
+
+                paste! {
+                    $(
+                        #[inline]
+                        fn [<set_ $field_name_method>](
+                            &mut self,
+                            pos: BlockPosition,
+                            value: $field_type
+                        ) -> Result<(), AccessError> {
+                            self.set_item(
+                                SectionField::$field_name_enum, pos, <$field_type as FieldType>::to_u64(value)
+                            )?;
+                            Ok(())
+                        }
+                    )*
+                }
+
+                #[inline]
+                fn item(&self, section_field: SectionField, pos: BlockPosition) -> Result<u64, BoundsError> {
+                    Self::check_bounds(pos)?;
+                    let stored: u64 = self.sections[section_field as usize].as_ref().map_or(Ok(0), |s| s.item(pos))?;
+                    Ok(stored ^ section_field.default_raw())
+                }
+
+                #[inline]
+                fn set_item(
+                    &mut self,
+                    section_field: SectionField,
+                    pos: BlockPosition,
+                    value: u64
+                ) -> Result<(), AccessError> {
+                    Self::check_bounds(pos)?;
+                    // values are checked against their bits by the chunk setters
+                    let section_index = section_field as usize;
+                    let stored: u64 = value ^ section_field.default_raw();
+
+                    if stored == 0 && self.sections[section_index].is_none() {
+                        return Ok(());
+                    }
+
+                    let section = self.sections[section_index].get_or_insert_with(||
+                        Section::new(section_field.bits())
+                    );
+
+                    section.set_item(pos, stored)?;
+
+                    if section.is_empty() {
+                        self.sections[section_index] = None;
+                    }
+
+                    Ok(())
+                }
+
+                #[inline]
+                fn is_empty(&self) -> bool {
+                    self.sections.iter().all(Option::is_none)
+                }
+
+                /// Checks positions relative to the subchunk, so absent sections are validated too.
+                #[inline]
+                fn check_bounds(pos: BlockPosition) -> Result<(), BoundsError> {
+                    let in_bounds: bool = pos.x >= 0 && pos.x < CHUNK_WIDTH as i32
+                        && pos.y >= 0 && pos.y < CHUNK_HEIGHT as i32
+                        && pos.z >= 0 && pos.z < SUBCHUNK_DEPTH as i32;
+
+                    if in_bounds {
+                        Ok(())
+                    } else {
+                        Err(BoundsError::OutOfBounds(pos))
+                    }
+                }
+            }
+
+            // -- SectionField --
+
+            #[derive(Clone, Copy)]
+            enum SectionField {
+                $($field_name_enum),*,
+                #[doc(hidden)]
+                __COUNT
+            }
+
+            impl SectionField {
+                const COUNT: usize = Self::__COUNT as usize;
+                const BITS_PER_ITEM_TABLE: &'static [u8] = &[$($bits_per_item),*];
+
+                const NAME_TABLE: &'static [&'static str] = &[$(stringify!($field_name_enum)),*];
+
+                const fn bits(&self) -> u8 {
+                    Self::BITS_PER_ITEM_TABLE[*self as usize]
+                }
+
+                fn infos() -> Vec<FieldInfo> {
+                    [$(Self::$field_name_enum),*]
+                        .iter()
+                        .map(|field| FieldInfo {
+                            name: Self::NAME_TABLE[*field as usize].to_string(),
+                            bits: field.bits(),
+                            default: field.default_raw(),
+                        })
+                        .collect()
+                }
+
+                /// Raw value the field reads back as wherever nothing else was set.
+                /// Sections store values relative to it, so unset blocks stay zero.
+                /// Checked to fit the field's bits at compile time.
+                #[inline]
+                fn default_raw(&self) -> u64 {
+                    match self {
+                        $(Self::$field_name_enum => [$(<$field_type as FieldType>::to_u64($default),)? 0][0],)*
+                        Self::__COUNT => unreachable!(),
+                    }
+                }
+
+                /// Returns an error if the passed raw value needs more bits than the field declares.
+                #[inline]
+                fn check_value(&self, value: u64) -> Result<(), AccessError> {
+                    let bits: u8 = self.bits();
+
+                    if bits < 64 && value >> bits != 0 {
+                        return Err(AccessError::ValueOutOfRange {
+                            field: Self::NAME_TABLE[*self as usize],
+                            value,
+                            bits,
+                        });
+                    }
+
+                    Ok(())
+                }
+
+                /// Converts a raw value of the field back to its type.
+                #[inline]
+                fn decode<T: FieldType>(&self, value: u64) -> Result<T, AccessError> {
+                    T::from_u64(value).ok_or(AccessError::InvalidValue {
+                        field: Self::NAME_TABLE[*self as usize],
+                        value,
+                    })
+                }
+            }
+
+            // every field must be able to read back the widest value it stores, including its default
+            const _: () = {
+                $(
+                    assert!(
+                        $bits_per_item <= <$field_type as FieldType>::BITS,
+                        concat!(
+                            "Field ", stringify!($field_name_enum),
+                            " declares more bits than ", stringify!($field_type), " can hold."
+                        ),
+                    );
+                    $(
+                        let default: $field_type = $default;
+                        let raw: u64 = $crate::core::raw_default(default as i128, <$field_type as FieldType>::ZIG_ZAG);
+                        assert!(
+                            $bits_per_item >= 64 || raw >> $bits_per_item == 0,
+                            concat!("Default of field ", stringify!($field_name_enum), " does not fit its bits."),
+                        );
+                    )?
+                )*
+            };
+        }
+    };
+    (
+        chunk_width: $chunk_width:expr,
+        chunk_height: $chunk_height:expr,
+        subchunk_depth: $subchunk_depth:expr,
+        num_subchunks: $num_subchunks:expr,
+        min_z: $min_z:expr,
+        $($fields:tt)*
+    ) => {
+        $crate::world! {
+            chunk_width: $chunk_width,
+            chunk_height: $chunk_height,
+            subchunk_depth: $subchunk_depth,
+            num_subchunks: $num_subchunks,
+            min_z: $min_z,
+            schema_version: 0,
+            migrations: {},
+            $($fields)*
+        }
+    };
+    (
+        chunk_width: $chunk_width:expr,
+        chunk_height: $chunk_height:expr,
+        subchunk_depth: $subchunk_depth:expr,
+        num_subchunks: $num_subchunks:expr,
+        $(
+            $(#[default = $default:expr])?
+            $field_name_enum:ident r#as $field_name_method:ident: $field_type:ty = $bits_per_item:expr
+        ),*
+        $(,)?
+    ) => {
+        $crate::world! {
+            chunk_width: $chunk_width,
+            chunk_height: $chunk_height,
+            subchunk_depth: $subchunk_depth,
+            num_subchunks: $num_subchunks,
+            schema_version: 0,
+            migrations: {},
+            $(
+                $(#[default = $default])?
+                $field_name_enum r#as $field_name_method: $field_type = $bits_per_item
+            ),*
+        }
+    };
+}
+
+#[cfg(all(test, feature = "async-io"))]
+mod tests {
+    use super::__internal_prelude::bincode::{config, serde::encode_to_vec};
+    use super::format::{
+        CHUNK_MAGIC, ChunkHeader, FORMAT_VERSION, decode_chunk_file, encode_chunk_file, fingerprint,
+    };
+    use super::migration::SubchunkRecord;
+    use super::prelude::*;
+    use super::storage::BoxFuture;
+    use std::{
+        io,
+        path::PathBuf,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+    use tokio::fs;
+
+    world! {
+        chunk_width: 16,
+        chunk_height: 16,
+        subchunk_depth: 16,
+        num_subchunks: 16,
+        Block r#as block: u8 = 8,
+        SkyLight r#as sky_light: u8 = 5,
+        Exposed r#as is_exposed: bool = 1,
+    }
+
+    mod schema_v0 {
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 2,
+            Block r#as block: u8 = 1,
+            Light r#as light: u8 = 4,
+            Temperature r#as temperature: u8 = 3,
+        }
+    }
+
+    mod wide_fields {
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 2,
+            Block r#as block: u16 = 12,
+            Temperature r#as temperature: i8 = 5,
+        }
+    }
+
+    #[cfg(feature = "derive")]
+    mod enum_fields {
+        use crate::core::FieldType;
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, FieldType)]
+        pub enum Facing {
+            North,
+            East,
+            South,
+            West,
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, FieldType)]
+        pub enum Kind {
+            #[field_type(fallback)]
+            Unknown,
+            Stone = 3,
+            Sand,
+        }
+
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 2,
+            Facing r#as facing: crate::tests::enum_fields::Facing = 2,
+            Kind r#as kind: crate::tests::enum_fields::Kind = 3,
+        }
+    }
+
+    // the same layout as `enum_fields`, storing the raw values of its enums
+    #[cfg(feature = "derive")]
+    mod raw_enum_fields {
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 2,
+            Facing r#as facing: u8 = 2,
+            Kind r#as kind: u8 = 3,
+        }
+    }
+
+    mod below_zero {
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 4,
+            min_z: -8,
+            Block r#as block: u8 = 4,
+        }
+    }
+
+    mod schema_v2 {
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 2,
+            schema_version: 2,
+            migrations: {
+                0 => [rename(Light, SkyLight), widen(Block, bits: 8)],
+                1 => [remove(Temperature), add(Exposed, bits: 1, default: 1)],
+            },
+            SkyLight r#as sky_light: u8 = 4,
+            Block r#as block: u8 = 8,
+            Exposed r#as is_exposed: bool = 1,
+        }
+    }
+
+    /// Returns a fresh directory unique to the calling test.
+    async fn test_root(name: &str) -> PathBuf {
+        let root: PathBuf = std::env::temp_dir().join(format!("terrain_data_{name}"));
+        if root.exists() {
+            fs::remove_dir_all(&root).await.unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_get_and_set_chunk() -> Result<(), AccessError> {
+        let mut chunk: Chunk = Chunk::default();
+        let pos_1: BlockPosition = BlockPosition::new(15, 1, 200);
+        let pos_2: BlockPosition = BlockPosition::new(3, 0, 2);
+
+        chunk.set_block(pos_1, 0)?;
+        chunk.set_block(pos_1, 4)?;
+        chunk.set_block(pos_2, 5)?;
+
+        assert_eq!(chunk.block(pos_1)?, 4);
+        assert_eq!(chunk.block(pos_2)?, 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_and_set_world() -> Result<(), AccessError> {
+        let world: Arc<World> = Arc::new(World::default());
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        world.add_chunk(chunk_pos, None).unwrap();
+
+        let pos_1: BlockPosition = BlockPosition::new(15, 1, 200);
+        let pos_2: BlockPosition = BlockPosition::new(3, 0, 2);
+
+        world.set_is_exposed(pos_1, true)?;
+        world.set_is_exposed(pos_1, false)?;
+        world.set_is_exposed(pos_2, true)?;
+
+        assert!(!world.is_exposed(pos_1)?);
+        assert!(world.is_exposed(pos_2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounds_are_validated() {
+        let mut chunk: Chunk = Chunk::default();
+
+        for pos in [
+            BlockPosition::new(-1, 0, 0),
+            BlockPosition::new(16, 0, 0),
+            BlockPosition::new(0, 16, 0),
+            BlockPosition::new(0, 0, -1),
+            BlockPosition::new(0, 0, 256),
+        ] {
+            assert!(!Chunk::contains(pos));
+            assert!(
+                matches!(chunk.block(pos), Err(AccessError::Bounds(BoundsError::OutOfBounds(p))) if p == pos)
+            );
+            assert!(chunk.set_block(pos, 1).is_err());
+        }
+
+        let world: World = World::default();
+        world.add_chunk(ChunkPosition::ZERO, None).unwrap();
+        let below: BlockPosition = BlockPosition::new(3, 3, -1);
+
+        match world.block(below) {
+            Err(AccessError::OutsideWorld { pos, min_z, max_z }) => {
+                assert_eq!((pos, min_z, max_z), (below, 0, 256));
+            }
+            other => panic!("expected an out of world error, got {other:?}"),
+        }
+        assert!(matches!(
+            world.set_block(BlockPosition::new(0, 0, 256), 1),
+            Err(AccessError::OutsideWorld { .. })
+        ));
+        assert_eq!(
+            World::check_bounds(below).unwrap_err().to_string(),
+            "Position IVec3(3, 3, -1) is outside the world, z must be within 0..256."
+        );
+    }
+
+    #[test]
+    fn test_values_must_fit_field_bits() {
+        let world: World = World::default();
+        world.add_chunk(ChunkPosition::ZERO, None).unwrap();
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        world.set_sky_light(pos, 31).unwrap();
+        assert!(matches!(
+            world.set_sky_light(pos, 200),
+            Err(AccessError::ValueOutOfRange {
+                field: "SkyLight",
+                value: 200,
+                bits: 5
+            })
+        ));
+        assert_eq!(world.sky_light(pos).unwrap(), 31);
+
+        let mut chunk: Chunk = Chunk::default();
+        let empty_size: usize = chunk.memory_size();
+        assert!(chunk.set_sky_light(pos, 32).is_err());
+        assert!(!chunk.is_dirty());
+        assert_eq!(chunk.memory_size(), empty_size);
+    }
+
+    #[test]
+    fn test_wide_and_signed_fields() {
+        use wide_fields::Chunk;
+
+        let mut chunk: Chunk = Chunk::default();
+        let pos: BlockPosition = BlockPosition::new(1, 2, 5);
+
+        chunk.set_block(pos, 4000).unwrap();
+        chunk.set_temperature(pos, -16).unwrap();
+        assert_eq!(chunk.block(pos).unwrap(), 4000);
+        assert_eq!(chunk.temperature(pos).unwrap(), -16);
+        assert_eq!(chunk.temperature(BlockPosition::ZERO).unwrap(), 0);
+
+        assert!(chunk.set_block(pos, 4096).is_err());
+        assert!(matches!(
+            chunk.set_temperature(pos, -17),
+            Err(AccessError::ValueOutOfRange {
+                field: "Temperature",
+                value: 33,
+                bits: 5
+            })
+        ));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_enum_fields() {
+        use enum_fields::{Chunk, Facing, Kind};
+
+        assert_eq!(
+            (<Facing as FieldType>::BITS, <Kind as FieldType>::BITS),
+            (2, 3)
+        );
+        assert_eq!(Kind::from_u64(4), Some(Kind::Sand));
+        assert_eq!(Kind::from_u64(6), Some(Kind::Unknown));
+        assert_eq!(Facing::from_u64(4), None);
+
+        let mut chunk: Chunk = Chunk::default();
+        let pos: BlockPosition = BlockPosition::new(3, 0, 7);
+
+        assert_eq!(chunk.facing(pos).unwrap(), Facing::North);
+        chunk.set_facing(pos, Facing::West).unwrap();
+        chunk.set_kind(pos, Kind::Sand).unwrap();
+        assert_eq!(chunk.facing(pos).unwrap(), Facing::West);
+        assert_eq!(chunk.kind(pos).unwrap(), Kind::Sand);
+    }
+
+    #[cfg(feature = "derive")]
+    #[tokio::test]
+    async fn test_enum_fields_round_trip() -> Result<(), ChunkStoreError> {
+        use enum_fields::{Facing, Kind};
+
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let raw_world: raw_enum_fields::World = raw_enum_fields::World::builder()
+            .storage(Arc::clone(&storage))
+            .build();
+        let world: enum_fields::World = enum_fields::World::builder()
+            .storage(Arc::clone(&storage))
+            .build();
+        let chunk_pos: ChunkPosition = ChunkPosition::ZERO;
+        let (pos_1, pos_2, pos_3): (BlockPosition, BlockPosition, BlockPosition) = (
+            BlockPosition::new(0, 1, 2),
+            BlockPosition::new(3, 0, 7),
+            BlockPosition::new(1, 1, 5),
+        );
+
+        raw_world.add_chunk(chunk_pos, None)?;
+        raw_world.set_facing(pos_1, 3)?;
+        raw_world.set_kind(pos_1, 4)?;
+        // no variant has the raw values 1 and 6, they decode to the fallback
+        raw_world.set_kind(pos_2, 1)?;
+        raw_world.set_kind(pos_3, 6)?;
+        raw_world.save_chunk(chunk_pos).await?;
+
+        world.load_chunk(chunk_pos).await?;
+        assert_eq!(world.facing(pos_1)?, Facing::West);
+        assert_eq!(world.kind(pos_1)?, Kind::Sand);
+        assert_eq!(world.kind(pos_2)?, Kind::Unknown);
+        assert_eq!(world.kind(pos_3)?, Kind::Unknown);
+
+        world.set_kind(pos_2, Kind::Stone)?;
+        world.save_chunk(chunk_pos).await?;
+        raw_world.unload_chunk(chunk_pos).await?;
+        raw_world.load_chunk(chunk_pos).await?;
+        assert_eq!(raw_world.kind(pos_2)?, 3);
+        assert_eq!(raw_world.kind(pos_3)?, 6);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_negative_min_z() -> Result<(), ChunkStoreError> {
+        use below_zero::{Chunk, MAX_Z, MIN_Z, World};
+
+        assert_eq!((MIN_Z, MAX_Z), (-8, 8));
+
+        let world: World = World::builder().storage(MemoryStorage::new()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::new(-1, 2);
+        world.add_chunk(chunk_pos, None)?;
+
+        let bottom: BlockPosition = World::chunk_to_block_pos(chunk_pos);
+        assert_eq!(bottom.z, -8);
+        world.set_block(bottom, 3)?;
+        world.set_block(bottom + BlockPosition::new(1, 1, 11), 9)?;
+        assert!(world.block(bottom - BlockPosition::Z).is_err());
+        assert!(world.set_block(BlockPosition::new(0, 0, MAX_Z), 1).is_err());
+
+        world.unload_chunk(chunk_pos).await?;
+        world.load_chunk(chunk_pos).await?;
+        assert_eq!(world.block(bottom)?, 3);
+        assert_eq!(world.block(BlockPosition::new(-3, 9, 3))?, 9);
+        assert_eq!(
+            World::chunk_coords(ChunkPosition::ZERO)
+                .map(|pos| pos.z)
+                .min(),
+            Some(-8)
+        );
+
+        let mut chunk: Chunk = Chunk::default();
+        chunk.set_block(BlockPosition::new(0, 0, -8), 1).unwrap();
+        chunk.set_block(BlockPosition::new(0, 0, 7), 2).unwrap();
+        assert_eq!(chunk.block(BlockPosition::new(0, 0, -8)).unwrap(), 1);
+        assert_eq!(chunk.block(BlockPosition::new(0, 0, 7)).unwrap(), 2);
+        assert!(chunk.block(BlockPosition::new(0, 0, 8)).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_save_load_chunk() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("save_load_chunk").await;
+        let world: Arc<World> = Arc::new(World::new(&root));
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        world.add_chunk(chunk_pos, None)?;
+        world.set_block(pos, 3)?;
+
+        world.unload_chunk(chunk_pos).await?;
+
+        assert!(world.block(pos).is_err());
+
+        world.load_chunk(chunk_pos).await?;
+
+        let block: u8 = world.block(pos)?;
+        assert!(block == 3);
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_worlds_with_separate_roots() -> Result<(), ChunkStoreError> {
+        let root_1: PathBuf = test_root("separate_roots_1").await;
+        let root_2: PathBuf = test_root("separate_roots_2").await;
+        let world_1: World = World::new(&root_1);
+        let world_2: World = World::builder().root(&root_2).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::new(-2, 7);
+        let pos: BlockPosition = World::chunk_to_block_pos(chunk_pos);
+
+        world_1.add_chunk(chunk_pos, None)?;
+        world_2.add_chunk(chunk_pos, None)?;
+        world_1.set_block(pos, 1)?;
+        world_2.set_block(pos, 2)?;
+
+        world_1.unload_chunk(chunk_pos).await?;
+        world_2.unload_chunk(chunk_pos).await?;
+
+        assert!(world_1.chunk_path(chunk_pos).unwrap().starts_with(&root_1));
+        assert_eq!(world_1.load_metadata().await?, World::metadata());
+
+        world_1.load_chunk(chunk_pos).await?;
+        world_2.load_chunk(chunk_pos).await?;
+
+        assert_eq!(world_1.block(pos)?, 1);
+        assert_eq!(world_2.block(pos)?, 2);
+
+        fs::remove_dir_all(&root_1).await?;
+        fs::remove_dir_all(&root_2).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_save_load_region() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("save_load_region").await;
+        let world: World = World::builder()
+            .root(&root)
+            .format(StorageFormat::Regions)
+            .build();
+        let chunk_positions: [ChunkPosition; 3] = [
+            ChunkPosition::new(0, 0),
+            ChunkPosition::new(5, 3),
+            ChunkPosition::new(40, 0),
+        ];
+
+        for (i, &chunk_pos) in chunk_positions.iter().enumerate() {
+            world.add_chunk(chunk_pos, None)?;
+            world.set_block(World::chunk_to_block_pos(chunk_pos), i as u8 + 1)?;
+            world.unload_chunk(chunk_pos).await?;
+        }
+
+        assert_eq!(
+            world.chunk_path(chunk_positions[0]),
+            world.chunk_path(chunk_positions[1])
+        );
+        assert_ne!(
+            world.chunk_path(chunk_positions[0]),
+            world.chunk_path(chunk_positions[2])
+        );
+
+        let mut stored_chunks: Vec<ChunkPosition> = world.stored_chunks().await?;
+        stored_chunks.sort_by_key(|pos| (pos.x, pos.y));
+        assert_eq!(stored_chunks, chunk_positions);
+
+        for (i, &chunk_pos) in chunk_positions.iter().enumerate() {
+            world.load_chunk(chunk_pos).await?;
+            assert_eq!(
+                world.block(World::chunk_to_block_pos(chunk_pos))?,
+                i as u8 + 1
+            );
+        }
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_save_chunk_and_dirty_tracking() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(MemoryStorage::new()).build();
+        let chunk_pos_1: ChunkPosition = ChunkPosition::new(0, 0);
+        let chunk_pos_2: ChunkPosition = ChunkPosition::new(1, 0);
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        world.add_chunk(chunk_pos_1, None)?;
+        world.add_chunk(chunk_pos_2, None)?;
+        assert_eq!(world.save_all_dirty().await?, 2);
+        assert!(world.dirty_chunks().is_empty());
+
+        world.set_block(pos, 3)?;
+        assert_eq!(world.dirty_chunks(), vec![chunk_pos_1]);
+
+        world.save_chunk(chunk_pos_1).await?;
+        assert!(!world.chunk(chunk_pos_1).unwrap().is_dirty());
+        assert_eq!(world.block(pos)?, 3);
+        assert_eq!(world.save_all_dirty().await?, 0);
+
+        world
+            .chunk_mut(chunk_pos_1)
+            .unwrap()
+            .set_block(pos, 4)
+            .unwrap();
+        assert_eq!(world.save_all_dirty().await?, 1);
+
+        world.unload_chunk(chunk_pos_1).await?;
+        world.load_chunk(chunk_pos_1).await?;
+        assert_eq!(world.block(pos)?, 4);
+        assert!(!world.chunk(chunk_pos_1).unwrap().is_dirty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_unload_keeps_chunk() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("failed_unload").await;
+        fs::write(&root, b"not a directory").await?;
+
+        let world: World = World::new(&root);
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos: BlockPosition = BlockPosition::new(4, 4, 4);
+
+        world.add_chunk(chunk_pos, None)?;
+        world.set_block(pos, 2)?;
+
+        assert!(world.unload_chunk(chunk_pos).await.is_err());
+        assert_eq!(world.block(pos)?, 2);
+        assert!(world.chunk(chunk_pos).unwrap().is_dirty());
+
+        fs::remove_file(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_replaces_atomically() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("atomic_write").await;
+        let storage: FileStorage = FileStorage::new(&root);
+        let chunk_pos: ChunkPosition = ChunkPosition::new(1, 1);
+
+        storage.write(chunk_pos, vec![1; 64]).await?;
+        storage.write(chunk_pos, vec![2; 8]).await?;
+
+        assert_eq!(storage.read(chunk_pos).await?, Some(vec![2; 8]));
+        assert_eq!(storage.list().await?, vec![chunk_pos]);
+
+        let mut entries = fs::read_dir(&root).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            assert_ne!(entry.path().extension().unwrap(), "tmp");
+        }
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_chunk_policies() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("corrupted").await;
+        let storage: FileStorage = FileStorage::new(&root);
+        let chunk_pos: ChunkPosition = ChunkPosition::new(2, -1);
+        let pos: BlockPosition = BlockPosition::new(32, -5, 9);
+
+        let world: World = World::new(&root);
+        world.add_chunk(chunk_pos, None)?;
+        world.set_sky_light(pos, 9)?;
+        world.unload_chunk(chunk_pos).await?;
+
+        // flip a single bit in the body
+        let mut data: Vec<u8> = storage.read(chunk_pos).await?.unwrap();
+        let last: usize = data.len() - 1;
+        data[last] ^= 0x01;
+        storage.write(chunk_pos, data).await?;
+
+        let result = world.load_chunk(chunk_pos).await;
+        assert!(matches!(
+            result,
+            Err(ChunkStoreError::Corrupted { pos, path: Some(ref path), .. })
+                if pos == chunk_pos && *path == storage.chunk_path(chunk_pos)
+        ));
+        assert!(!world.is_chunk_at_pos(chunk_pos));
+
+        let regenerating: World = World::builder()
+            .root(&root)
+            .corruption_policy(CorruptionPolicy::Regenerate)
+            .build();
+        regenerating.load_chunk(chunk_pos).await?;
+        assert_eq!(regenerating.sky_light(pos)?, 0);
+        assert!(regenerating.chunk(chunk_pos).unwrap().is_dirty());
+
+        let quarantining: World = World::builder()
+            .root(&root)
+            .corruption_policy(CorruptionPolicy::Quarantine)
+            .build();
+        assert!(matches!(
+            quarantining.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::Corrupted { .. })
+        ));
+        assert!(storage.read(chunk_pos).await?.is_none());
+        assert!(fs::try_exists(storage.quarantine_path(chunk_pos)).await?);
+        assert!(storage.list().await?.is_empty());
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncated_region_is_corruption() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("truncated_region").await;
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        let world: World = World::builder()
+            .root(&root)
+            .format(StorageFormat::Regions)
+            .build();
+        world.add_chunk(chunk_pos, None)?;
+        world.set_sky_light(pos, 9)?;
+        world.unload_chunk(chunk_pos).await?;
+
+        // keep the header but drop every blob it points at
+        let path: PathBuf = world.storage().path(chunk_pos).unwrap();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .set_len(8192)?;
+
+        assert!(matches!(
+            World::builder()
+                .root(&root)
+                .format(StorageFormat::Regions)
+                .build()
+                .load_chunk(chunk_pos)
+                .await,
+            Err(ChunkStoreError::Corrupted {
+                source: CorruptionError::RegionEntry { .. },
+                ..
+            })
+        ));
+
+        let regenerating: World = World::builder()
+            .root(&root)
+            .format(StorageFormat::Regions)
+            .corruption_policy(CorruptionPolicy::Regenerate)
+            .build();
+        regenerating.load_chunk(chunk_pos).await?;
+        assert_eq!(regenerating.sky_light(pos)?, 0);
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_older_formats_are_not_regenerated() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: World = World::builder()
+            .storage(Arc::clone(&storage))
+            .corruption_policy(CorruptionPolicy::Regenerate)
+            .build();
+        let chunk_pos: ChunkPosition = ChunkPosition::ZERO;
+
+        let mut data: Vec<u8> = CHUNK_MAGIC.to_vec();
+        data.extend_from_slice(&(FORMAT_VERSION - 1).to_le_bytes());
+        data.extend_from_slice(&[0; 16]);
+        storage.write(chunk_pos, data.clone()).await?;
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::InvalidHeader {
+                source: HeaderError::UnsupportedVersion(version),
+                ..
+            }) if version == FORMAT_VERSION - 1
+        ));
+        assert!(!world.is_chunk_at_pos(chunk_pos));
+        assert_eq!(storage.read(chunk_pos).await?, Some(data));
+
+        // a chunk saved before chunk files had a header, its first subchunk is present
+        let legacy: Vec<u8> = vec![1, 0, 0, 0];
+        storage.write(chunk_pos, legacy.clone()).await?;
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::InvalidHeader {
+                source: HeaderError::LegacyFormat,
+                ..
+            })
+        ));
+        assert!(!world.is_chunk_at_pos(chunk_pos));
+        assert_eq!(storage.read(chunk_pos).await?, Some(legacy));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_rejects_other_schema() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(MemoryStorage::new()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+
+        let other: ChunkSchema = ChunkSchema {
+            fingerprint: fingerprint(&["Block"], &[1]),
+            ..SCHEMA
+        };
+        let data: Vec<u8> = encode_chunk_file(
+            &ChunkHeader {
+                schema: other,
+                schema_version: SCHEMA_VERSION,
+                fields: Vec::new(),
+                compression: Compression::None,
+                generation_stage: 0,
+            },
+            &[],
+        )?;
+        world.storage().write(chunk_pos, data).await?;
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::SchemaMismatch { found, .. }) if found == other
+        ));
+
+        world
+            .storage()
+            .write(chunk_pos, b"not a chunk file".to_vec())
+            .await?;
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::InvalidHeader {
+                source: HeaderError::BadMagic,
+                ..
+            })
+        ));
+        assert!(!world.is_chunk_at_pos(chunk_pos));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_sections_are_corruption() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: World = World::builder().storage(Arc::clone(&storage)).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        world.add_chunk(chunk_pos, None)?;
+        world.unload_chunk(chunk_pos).await?;
+        let data: Vec<u8> = storage.read(chunk_pos).await?.unwrap();
+        let (header, _) = decode_chunk_file(&data).unwrap();
+
+        // one section short of the header's field list, behind a valid checksum
+        let mut subchunks: Vec<SubchunkRecord<16, 16, 16>> = vec![None; 16];
+        subchunks[3] = Some(vec![None; header.fields.len() - 1]);
+        let body: Vec<u8> = encode_to_vec(&subchunks, config::standard()).unwrap();
+        world
+            .storage()
+            .write(chunk_pos, encode_chunk_file(&header, &body)?)
+            .await?;
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::Corrupted {
+                source: CorruptionError::SectionCount {
+                    expected: 3,
+                    found: 2
+                },
+                ..
+            })
+        ));
+
+        let mut duplicated: ChunkHeader = header.clone();
+        duplicated.fields[1].name = "Block".to_string();
+        let subchunks: Vec<SubchunkRecord<16, 16, 16>> = vec![None; 16];
+        let body: Vec<u8> = encode_to_vec(&subchunks, config::standard()).unwrap();
+        world
+            .storage()
+            .write(chunk_pos, encode_chunk_file(&duplicated, &body)?)
+            .await?;
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::Corrupted {
+                source: CorruptionError::DuplicateField(field),
+                ..
+            }) if field == "Block"
+        ));
+
+        let regenerating: World = World::builder()
+            .storage(storage)
+            .corruption_policy(CorruptionPolicy::Regenerate)
+            .build();
+        regenerating.load_chunk(chunk_pos).await?;
+        assert!(regenerating.chunk(chunk_pos).unwrap().is_dirty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migrate_older_schema() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("migrate").await;
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos_1: BlockPosition = BlockPosition::new(1, 1, 1);
+        let pos_2: BlockPosition = BlockPosition::new(2, 3, 7);
+
+        let old_world: schema_v0::World = schema_v0::World::new(&root);
+        old_world.add_chunk(chunk_pos, None)?;
+        old_world.set_block(pos_1, 1)?;
+        old_world.set_light(pos_2, 9)?;
+        old_world.set_temperature(pos_2, 5)?;
+        old_world.unload_chunk(chunk_pos).await?;
+
+        let new_world: schema_v2::World = schema_v2::World::new(&root);
+        new_world.load_chunk(chunk_pos).await?;
+
+        assert_eq!(new_world.block(pos_1)?, 1);
+        assert_eq!(new_world.sky_light(pos_2)?, 9);
+        assert!(new_world.is_exposed(pos_1)? && new_world.is_exposed(pos_2)?);
+        assert!(new_world.chunk(chunk_pos).unwrap().is_dirty());
+
+        // the file is untouched until the chunk is saved, so older worlds still read it
+        new_world.unload_chunk(chunk_pos).await?;
+        assert!(matches!(
+            old_world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::Migration {
+                source: MigrationError::NewerVersion {
+                    found: 2,
+                    current: 0
+                },
+                ..
+            })
+        ));
+
+        let rewriting_world: schema_v2::World = schema_v2::World::builder()
+            .root(&root)
+            .rewrite_migrated(true)
+            .build();
+        rewriting_world.load_chunk(chunk_pos).await?;
+
+        assert!(!rewriting_world.chunk(chunk_pos).unwrap().is_dirty());
+        assert_eq!(rewriting_world.block(pos_1)?, 1);
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migration_rewrites_file() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("migrate_rewrite").await;
+        let chunk_pos: ChunkPosition = ChunkPosition::new(-1, 2);
+
+        let old_world: schema_v0::World = schema_v0::World::new(&root);
+        old_world.add_chunk(chunk_pos, None)?;
+        old_world.unload_chunk(chunk_pos).await?;
+
+        let new_world: schema_v2::World = schema_v2::World::builder()
+            .root(&root)
+            .rewrite_migrated(true)
+            .build();
+        new_world.load_chunk(chunk_pos).await?;
+
+        let data: Vec<u8> = new_world.storage().read(chunk_pos).await?.unwrap();
+        let (header, _) = decode_chunk_file(&data).unwrap();
+        assert_eq!(header.schema_version, schema_v2::SCHEMA_VERSION);
+        assert_eq!(header.schema, schema_v2::SCHEMA);
+
+        fs::remove_dir_all(&root).await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "lz4")]
+    #[tokio::test]
+    async fn test_compressed_chunks() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos: BlockPosition = BlockPosition::new(7, 7, 7);
+
+        let world: World = World::builder()
+            .storage(Arc::clone(&storage))
+            .compression(Compression::Lz4)
+            .build();
+        world.add_chunk(chunk_pos, None)?;
+
+        for pos in World::chunk_coords(chunk_pos).filter(|pos| pos.z < 64) {
+            world.set_sky_light(pos, 15)?;
+        }
+        world.set_block(pos, 1)?;
+        world.save_chunk(chunk_pos).await?;
+
+        let compressed: Vec<u8> = storage.read(chunk_pos).await?.unwrap();
+        let (header, _) = decode_chunk_file(&compressed).unwrap();
+        assert_eq!(header.compression, Compression::Lz4);
+
+        // chunks written with another codec stay readable after switching
+        let plain_world: World = World::builder().storage(Arc::clone(&storage)).build();
+        plain_world.load_chunk(chunk_pos).await?;
+        assert_eq!(plain_world.block(pos)?, 1);
+        assert_eq!(plain_world.sky_light(BlockPosition::new(0, 0, 63))?, 15);
+
+        plain_world
+            .chunk_mut(chunk_pos)
+            .unwrap()
+            .set_block(pos, 0)
+            .unwrap();
+        plain_world.save_chunk(chunk_pos).await?;
+        assert!(storage.read(chunk_pos).await?.unwrap().len() > compressed.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_or_else() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(MemoryStorage::new()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::new(-2, 5);
+        let pos: BlockPosition = BlockPosition::new(-20, 90, 3);
+
+        assert!(matches!(
+            world.load_chunk(chunk_pos).await,
+            Err(ChunkStoreError::NotStored(p)) if p == chunk_pos
+        ));
+
+        world
+            .load_or_else(chunk_pos, || {
+                let mut chunk: Chunk = Chunk::default();
+                chunk.set_block(World::global_to_local_pos(pos), 1).unwrap();
+                chunk
+            })
+            .await?;
+        assert_eq!(world.block(pos)?, 1);
+        assert!(world.chunk(chunk_pos).unwrap().is_dirty());
+
+        // already loaded
+        world.load_or_else(chunk_pos, || unreachable!()).await?;
+
+        world.unload_chunk(chunk_pos).await?;
+        world.load_or_else(chunk_pos, || unreachable!()).await?;
+        assert_eq!(world.block(pos)?, 1);
+        assert!(!world.chunk(chunk_pos).unwrap().is_dirty());
+
+        Ok(())
+    }
+
+    /// Places a single block per column at a height derived from the seed.
+    struct ColumnGenerator {
+        seed: u64,
+    }
+
+    impl ChunkGenerator<Chunk> for ColumnGenerator {
+        fn seed(&self) -> u64 {
+            self.seed
+        }
+
+        fn generate(&self, pos: ChunkPosition) -> Result<Chunk, AccessError> {
+            let mut chunk: Chunk = Chunk::default();
+
+            for local_pos in World::chunk_coords(ChunkPosition::ZERO).filter(|p| p.z == 0) {
+                let height: i32 = ((self.seed as i32 + pos.x + local_pos.x) * 7).rem_euclid(64);
+                chunk.set_block(local_pos.with_z(height), 1)?;
+            }
+
+            Ok(chunk)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_get_or_generate() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let chunk_pos: ChunkPosition = ChunkPosition::new(1, 0);
+        let pos: BlockPosition = BlockPosition::new(16, 0, 21);
+
+        let plain: World = World::builder().storage(Arc::clone(&storage)).build();
+        assert!(matches!(
+            plain.get_or_generate(chunk_pos).await,
+            Err(ChunkStoreError::NoGenerator)
+        ));
+
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(Arc::clone(&storage))
+                .generator(ColumnGenerator { seed: 2 })
+                .build(),
+        );
+        assert_eq!(world.generator().unwrap().seed(), 2);
+
+        world.get_or_generate(chunk_pos).await?;
+        assert_eq!(world.block(pos)?, 1);
+        assert!(world.chunk(chunk_pos).unwrap().is_dirty());
+
+        // saved chunks are loaded instead of generated again
+        world.set_block(pos, 0)?;
+        world.unload_chunk(chunk_pos).await?;
+        world.get_or_generate(chunk_pos).await?;
+        assert_eq!(world.block(pos)?, 0);
+
+        let positions: Vec<ChunkPosition> =
+            World::positions_in_square(ChunkPosition::ZERO, 3).collect();
+        world.get_or_generate_all(positions.iter().copied()).await?;
+
+        for &other in positions.iter().filter(|&&other| other != chunk_pos) {
+            let origin: BlockPosition = World::chunk_to_block_pos(other);
+            let height: i32 = ((2 + other.x) * 7).rem_euclid(64);
+            assert_eq!(world.block(origin.with_z(height))?, 1);
+        }
+
+        Ok(())
+    }
+
+    /// Fills the bottom layer of a chunk.
+    struct GroundStage {
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl GenerationStage<World> for GroundStage {
+        fn apply(&self, world: &World, pos: ChunkPosition) -> Result<(), AccessError> {
+            self.runs.fetch_add(1, Ordering::Relaxed);
+
+            for block_pos in World::chunk_coords(pos).filter(|p| p.z == 0) {
+                world.set_block(block_pos, 1)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Places a block on the ground of every neighboring chunk, which needs their ground first.
+    struct SpillStage {
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl GenerationStage<World> for SpillStage {
+        fn radius(&self) -> u32 {
+            1
+        }
+
+        fn apply(&self, world: &World, pos: ChunkPosition) -> Result<(), AccessError> {
+            self.runs.fetch_add(1, Ordering::Relaxed);
+
+            for neighbor in World::positions_in_square(pos, 1) {
+                assert!(world.chunk(neighbor)?.generation_stage() >= 1);
+
+                let ground: BlockPosition = World::chunk_to_block_pos(neighbor);
+                assert_eq!(world.block(ground)?, 1);
+                world.set_block(ground.with_z(1), 2)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_generation_stages_wait_for_neighbors() -> Result<(), ChunkStoreError> {
+        let ground_runs: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let spill_runs: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(Arc::clone(&storage))
+                .stage(GroundStage {
+                    runs: Arc::clone(&ground_runs),
+                })
+                .stage(SpillStage {
+                    runs: Arc::clone(&spill_runs),
+                })
+                .build(),
+        );
+        let origin: ChunkPosition = ChunkPosition::ZERO;
+
+        world.generate_to(origin, 2).await?;
+        assert_eq!(ground_runs.load(Ordering::Relaxed), 9);
+        assert_eq!(spill_runs.load(Ordering::Relaxed), 1);
+        assert_eq!(world.chunk(origin).unwrap().generation_stage(), 2);
+        assert_eq!(
+            world
+                .chunk(ChunkPosition::new(1, 1))
+                .unwrap()
+                .generation_stage(),
+            1
+        );
+        assert_eq!(world.block(BlockPosition::new(-16, -16, 1))?, 2);
+
+        // completed stages are not run again
+        world.generate_to(ChunkPosition::new(1, 0), 2).await?;
+        assert_eq!(ground_runs.load(Ordering::Relaxed), 12);
+        assert_eq!(spill_runs.load(Ordering::Relaxed), 2);
+        world.generate_to(origin, 2).await?;
+        assert_eq!(spill_runs.load(Ordering::Relaxed), 2);
+
+        // the stage is saved with the chunk
+        world.unload_chunk(origin).await?;
+        world.load_chunk(origin).await?;
+        assert_eq!(world.chunk(origin).unwrap().generation_stage(), 2);
+
+        Ok(())
+    }
 
-                    if section.is_empty() {
-                        self.sections[section_index] = None;
-                    }
+    /// Records whether its chunk is pinned, then fails if asked to.
+    struct PinCheckStage {
+        pinned: Arc<AtomicBool>,
+        fail: bool,
+    }
 
-                    Ok(())
-                }
+    impl GenerationStage<World> for PinCheckStage {
+        fn apply(&self, world: &World, pos: ChunkPosition) -> Result<(), AccessError> {
+            self.pinned.store(world.is_pinned(pos), Ordering::Relaxed);
 
-                #[inline]
-                fn is_empty(&self) -> bool {
-                    self.sections.iter().all(Option::is_none)
-                }
+            if self.fail {
+                return Err(ChunkAccessError::ChunkUnloaded(pos).into());
             }
 
-            // -- SectionField --
+            Ok(())
+        }
+    }
 
-            #[derive(Clone, Copy, Serialize, Deserialize)]
-            enum SectionField {
-                $($field_name_enum),*,
-                #[doc(hidden)]
-                __COUNT
-            }
+    #[tokio::test]
+    async fn test_generated_chunk_stays_pinned() -> Result<(), ChunkStoreError> {
+        for fail in [false, true] {
+            let pinned: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+            let world: Arc<World> = Arc::new(
+                World::builder()
+                    .storage(MemoryStorage::new())
+                    .memory_budget(MemoryBudget::Chunks(0))
+                    .stage(PinCheckStage {
+                        pinned: Arc::clone(&pinned),
+                        fail,
+                    })
+                    .build(),
+            );
+            let pos: ChunkPosition = ChunkPosition::ZERO;
+
+            let result: Result<(), ChunkStoreError> = world.generate_to(pos, 1).await;
+            assert_eq!(result.is_err(), fail);
+            assert!(pinned.load(Ordering::Relaxed));
+            // the pin is released on every exit
+            assert!(!world.is_pinned(pos));
+            assert_eq!(world.evict().await?, vec![pos]);
+        }
 
-            impl SectionField {
-                const COUNT: usize = Self::__COUNT as usize;
-                const BITS_PER_ITEM_TABLE: &'static [u8] = &[$($bits_per_item),*];
+        Ok(())
+    }
 
-                const fn bits(&self) -> u8 {
-                    Self::BITS_PER_ITEM_TABLE[*self as usize]
+    /// Counts the times it ran while a chunk within twice its radius was running it too.
+    #[derive(Clone)]
+    struct OverlapStage {
+        running: Arc<Mutex<Vec<ChunkPosition>>>,
+        overlaps: Arc<AtomicUsize>,
+    }
+
+    impl GenerationStage<World> for OverlapStage {
+        fn radius(&self) -> u32 {
+            1
+        }
+
+        fn apply(&self, _world: &World, pos: ChunkPosition) -> Result<(), AccessError> {
+            {
+                let mut running = self.running.lock().unwrap();
+                if running
+                    .iter()
+                    .any(|other| (*other - pos).abs().max_element() <= 2)
+                {
+                    self.overlaps.fetch_add(1, Ordering::Relaxed);
                 }
+                running.push(pos);
             }
-        }
-    };
-}
 
-#[cfg(test)]
-mod tests {
-    use super::prelude::*;
-    use std::sync::Arc;
-    use tokio::fs;
+            std::thread::sleep(Duration::from_millis(10));
+            self.running.lock().unwrap().retain(|&other| other != pos);
 
-    world! {
-        chunk_width: 16,
-        chunk_height: 16,
-        subchunk_depth: 16,
-        num_subchunks: 16,
-        Block r#as block: u8 = 1,
-        SkyLight r#as sky_light: u8 = 5,
-        Exposed r#as is_exposed: bool = 1,
+            Ok(())
+        }
     }
 
-    #[test]
-    fn test_get_and_set_chunk() -> Result<(), BoundsError> {
-        let mut chunk: Chunk = Chunk::default();
-        let pos_1: BlockPosition = BlockPosition::new(15, 1, 200);
-        let pos_2: BlockPosition = BlockPosition::new(3, 0, 2);
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_overlapping_stages_run_one_at_a_time() -> Result<(), ChunkStoreError> {
+        let stage: OverlapStage = OverlapStage {
+            running: Arc::new(Mutex::new(Vec::new())),
+            overlaps: Arc::new(AtomicUsize::new(0)),
+        };
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(MemoryStorage::new())
+                .stage(stage.clone())
+                .stage(stage.clone())
+                .build(),
+        );
+
+        // the first stage runs on all 9 chunks around the origin, whose squares all overlap
+        world.generate_to(ChunkPosition::ZERO, 2).await?;
+        assert_eq!(stage.overlaps.load(Ordering::Relaxed), 0);
 
-        chunk.set_block(pos_1, 0)?;
-        chunk.set_block(pos_1, 4)?;
-        chunk.set_block(pos_2, 5)?;
+        Ok(())
+    }
 
-        assert_eq!(chunk.block(pos_1)?, 4);
-        assert_eq!(chunk.block(pos_2)?, 5);
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_generation_survives_eviction() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(Arc::clone(&storage))
+                .memory_budget(MemoryBudget::Chunks(2))
+                .stage(GroundStage {
+                    runs: Arc::new(AtomicUsize::new(0)),
+                })
+                .stage(SpillStage {
+                    runs: Arc::new(AtomicUsize::new(0)),
+                })
+                .build(),
+        );
+        let evictor = world.spawn_evictor();
+        let origin: ChunkPosition = ChunkPosition::ZERO;
+
+        // the square of 9 chunks the stages touch is over the budget the whole time
+        world.generate_to(origin, 2).await?;
+        assert!(World::positions_in_square(origin, 1).all(|pos| !world.is_pinned(pos)));
+
+        evictor.abort();
+        let _ = evictor.await;
+        world.evict().await?;
+        assert!(
+            World::positions_in_square(origin, 1)
+                .filter(|&pos| world.is_chunk_at_pos(pos))
+                .count()
+                <= 2
+        );
+
+        let corner: ChunkPosition = ChunkPosition::new(-1, -1);
+        world.load_or_default(origin).await?;
+        world.load_or_default(corner).await?;
+        assert_eq!(world.chunk(origin).unwrap().generation_stage(), 2);
+        assert_eq!(world.chunk(corner).unwrap().generation_stage(), 1);
+        assert_eq!(world.block(BlockPosition::new(-16, -16, 1))?, 2);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_get_and_set_world() -> Result<(), AccessError> {
-        let world: Arc<World> = Arc::new(World::default());
-        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
-        world.add_chunk(chunk_pos, None).unwrap();
-
-        let pos_1: BlockPosition = BlockPosition::new(15, 1, 200);
-        let pos_2: BlockPosition = BlockPosition::new(3, 0, 2);
-
-        world.set_is_exposed(pos_1, true)?;
-        world.set_is_exposed(pos_1, false)?;
-        world.set_is_exposed(pos_2, true)?;
+    async fn test_generator_errors_are_returned() {
+        let world: World = World::builder()
+            .storage(MemoryStorage::new())
+            .generator(HeightmapGenerator::new(7, |chunk: &mut Chunk, pos| {
+                chunk.set_sky_light(pos, 200)
+            }))
+            .build();
+
+        assert!(matches!(
+            world.get_or_generate(ChunkPosition::ZERO).await,
+            Err(ChunkStoreError::Access(AccessError::ValueOutOfRange {
+                field: "SkyLight",
+                ..
+            }))
+        ));
+        assert!(!world.is_chunk_at_pos(ChunkPosition::ZERO));
+    }
 
-        assert_eq!(world.is_exposed(pos_1)?, false);
-        assert_eq!(world.is_exposed(pos_2)?, true);
+    #[test]
+    fn test_heightmap_golden_values() {
+        let generator: HeightmapGenerator<Chunk> =
+            HeightmapGenerator::new(42, |chunk: &mut Chunk, pos| chunk.set_block(pos, 1))
+                .amplitude(96.0);
+        let chunk_pos: ChunkPosition = ChunkPosition::new(1, -2);
+        let chunk: Chunk = generator.generate(chunk_pos).unwrap();
+
+        // output must never change, as worlds rely on regenerating identical chunks
+        for ((x, y), height) in [((0, 0), 118), ((5, 9), 129), ((15, 15), 132), ((8, 2), 122)] {
+            assert_eq!(generator.height(16 + x, -32 + y), height);
+            assert_eq!(
+                chunk
+                    .block(BlockPosition::new(x, y, height as i32 - 1))
+                    .unwrap(),
+                1
+            );
+            assert_eq!(
+                chunk
+                    .block(BlockPosition::new(x, y, height as i32))
+                    .unwrap(),
+                0
+            );
+        }
 
-        Ok(())
+        let filled: usize = World::chunk_coords(ChunkPosition::ZERO)
+            .filter(|&pos| chunk.block(pos).unwrap() == 1)
+            .count();
+        assert_eq!(filled, 32665);
     }
 
     #[tokio::test]
-    async fn test_save_load_chunk() -> Result<(), ChunkStoreError> {
-        let world: Arc<World> = Arc::new(World::default());
-        let chunk_pos: ChunkPosition = ChunkPosition::new(0, 0);
-        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+    async fn test_memory_storage() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(MemoryStorage::new()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::new(3, -4);
+        let pos: BlockPosition = World::chunk_to_block_pos(chunk_pos) + BlockPosition::new(1, 1, 1);
 
         world.add_chunk(chunk_pos, None)?;
-        world.set_block(pos, 3)?;
-
+        world.set_sky_light(pos, 12)?;
         world.unload_chunk(chunk_pos).await?;
 
-        assert!(world.block(pos).is_err());
+        assert_eq!(world.chunk_path(chunk_pos), None);
+        assert_eq!(world.stored_chunks().await?, vec![chunk_pos]);
+        assert_eq!(world.load_metadata().await?, World::metadata());
 
         world.load_chunk(chunk_pos).await?;
-
-        let block: u8 = world.block(pos)?;
-        assert!(block == 3);
-
-        if std::path::Path::new(CHUNKS_DIR).exists() {
-            fs::remove_dir_all(CHUNKS_DIR).await.unwrap();
-        }
+        assert_eq!(world.sky_light(pos)?, 12);
 
         Ok(())
     }
@@ -542,8 +3485,6 @@ mod tests {
     #[tokio::test]
     async fn test_concurrent_set_block_and_add_chunk()
     -> Result<(), Box<dyn std::error::Error + Send>> {
-        tokio::fs::create_dir_all(CHUNKS_DIR).await.unwrap();
-
         let world: Arc<World> = Arc::new(World::default());
 
         let world_clone1: Arc<World> = Arc::clone(&world);
@@ -579,11 +3520,573 @@ mod tests {
 
         assert_eq!(world.block(pos).unwrap(), 5);
 
-        if std::path::Path::new(CHUNKS_DIR).exists() {
-            fs::remove_dir_all(CHUNKS_DIR).await.unwrap();
+        Ok(())
+    }
+
+    /// Memory storage that counts reads and yields inside every call,
+    /// so concurrent operations interleave.
+    #[derive(Default)]
+    struct YieldingStorage {
+        inner: MemoryStorage,
+        reads: AtomicUsize,
+    }
+
+    impl ChunkStorage for YieldingStorage {
+        fn read(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+            Box::pin(async move {
+                self.reads.fetch_add(1, Ordering::Relaxed);
+                tokio::task::yield_now().await;
+                self.inner.read(pos).await
+            })
+        }
+
+        fn write(&self, pos: ChunkPosition, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                self.inner.write(pos, data).await
+            })
+        }
+
+        fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+            self.inner.read_blocking(pos)
+        }
+
+        fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()> {
+            self.inner.write_blocking(pos, data)
+        }
+
+        fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+            self.inner.delete_blocking(pos)
+        }
+
+        fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>> {
+            self.inner.list_blocking()
+        }
+
+        fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>> {
+            self.inner.read_metadata_blocking()
+        }
+
+        fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()> {
+            self.inner.write_metadata_blocking(data)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_loads_share_one_read() -> Result<(), ChunkStoreError> {
+        let storage: Arc<YieldingStorage> = Arc::new(YieldingStorage::default());
+        let world: Arc<World> = Arc::new(World::builder().storage(Arc::clone(&storage)).build());
+        let chunk_pos: ChunkPosition = ChunkPosition::new(3, 3);
+        let pos: BlockPosition = BlockPosition::new(50, 50, 50);
+
+        world.add_chunk(chunk_pos, None)?;
+        world.set_block(pos, 1)?;
+        world.unload_chunk(chunk_pos).await?;
+        storage.reads.store(0, Ordering::Relaxed);
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let world: Arc<World> = Arc::clone(&world);
+                tokio::spawn(async move { world.load_chunk(chunk_pos).await })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.await.unwrap()?;
+        }
+
+        assert_eq!(storage.reads.load(Ordering::Relaxed), 1);
+        assert_eq!(world.block(pos)?, 1);
+        assert_eq!(world.chunk_state(chunk_pos), ChunkState::Loaded);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_waits_for_unload() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(YieldingStorage::default()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::ZERO;
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        world.add_chunk(chunk_pos, None)?;
+        world.set_block(pos, 1)?;
+
+        let (unloaded, loaded) = tokio::join!(world.unload_chunk(chunk_pos), async {
+            tokio::task::yield_now().await;
+            assert_eq!(world.chunk_state(chunk_pos), ChunkState::Saving);
+            world.load_chunk(chunk_pos).await
+        });
+        unloaded?;
+        loaded?;
+
+        assert_eq!(world.block(pos)?, 1);
+        assert!(!world.chunk(chunk_pos).unwrap().is_dirty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unload_keeps_edits_made_during_write() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(YieldingStorage::default()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::ZERO;
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        world.add_chunk(chunk_pos, None)?;
+        world.set_block(pos, 1)?;
+
+        let (unloaded, edited) = tokio::join!(world.unload_chunk(chunk_pos), async {
+            tokio::task::yield_now().await;
+            // still loaded while its data is written
+            world.set_block(pos, 2)
+        });
+        unloaded?;
+        edited?;
+
+        assert!(world.chunk(chunk_pos).is_err());
+        world.load_chunk(chunk_pos).await?;
+        assert_eq!(world.block(pos)?, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_keeps_chunk_added_meanwhile() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder().storage(YieldingStorage::default()).build();
+        let chunk_pos: ChunkPosition = ChunkPosition::ZERO;
+        let pos: BlockPosition = BlockPosition::new(1, 2, 3);
+
+        world.add_chunk(chunk_pos, None)?;
+        world.set_block(pos, 1)?;
+        world.unload_chunk(chunk_pos).await?;
+
+        let (loaded, _) = tokio::join!(world.load_chunk(chunk_pos), async {
+            assert_eq!(world.chunk_state(chunk_pos), ChunkState::Loading);
+            world.add_chunk(chunk_pos, None).unwrap();
+            world.set_block(pos, 2).unwrap();
+        });
+
+        assert!(matches!(
+            loaded,
+            Err(ChunkStoreError::ChunkOverwrite(
+                ChunkOverwriteError::ChunkAlreadyLoaded(_)
+            ))
+        ));
+        assert_eq!(world.block(pos)?, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ticket_manager_drives_world() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: World = World::builder().storage(Arc::clone(&storage)).build();
+        let tickets: ChunkTicketManager<World> =
+            ChunkTicketManager::new(Arc::new(world)).grace_period(Duration::ZERO);
+        let viewer: ViewerId = tickets.add_viewer(ChunkPosition::ZERO, 1, ViewShape::Square);
+
+        // loaded by other means, so the manager takes no ticket for it
+        tickets.world().add_chunk(ChunkPosition::ZERO, None)?;
+
+        let report: TickReport = tickets.tick().await;
+        assert!(report.errors.is_empty());
+        assert_eq!(report.loaded.len(), 8);
+        assert!(tickets.world().chunk(ChunkPosition::new(1, 1)).is_ok());
+        assert!(!tickets.has_ticket(ChunkPosition::ZERO));
+
+        tickets.remove_viewer(viewer);
+        let report: TickReport = tickets.tick().await;
+        assert!(report.errors.is_empty());
+        assert_eq!(report.unloaded.len(), 8);
+        assert!(tickets.world().chunk(ChunkPosition::new(1, 1)).is_err());
+        assert!(tickets.world().chunk(ChunkPosition::ZERO).is_ok());
+        assert_eq!(storage.len(), 8);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_manager_reloads_evicted_chunks() -> Result<(), ChunkStoreError> {
+        let world: World = World::builder()
+            .storage(Arc::new(MemoryStorage::new()))
+            .memory_budget(MemoryBudget::Chunks(1))
+            .build();
+        let tickets: ChunkTicketManager<World> = ChunkTicketManager::new(Arc::new(world));
+        tickets.add_viewer(ChunkPosition::ZERO, 0, ViewShape::Square);
+
+        assert_eq!(tickets.tick().await.loaded, vec![ChunkPosition::ZERO]);
+
+        tickets.world().add_chunk(ChunkPosition::new(5, 0), None)?;
+        assert_eq!(tickets.world().evict().await?, vec![ChunkPosition::ZERO]);
+        assert!(tickets.has_ticket(ChunkPosition::ZERO));
+
+        let report: TickReport = tickets.tick().await;
+        assert!(report.errors.is_empty());
+        assert_eq!(report.loaded, vec![ChunkPosition::ZERO]);
+        assert!(tickets.world().is_chunk_at_pos(ChunkPosition::ZERO));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget_evicts_least_recently_used() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: World = World::builder()
+            .storage(Arc::clone(&storage))
+            .memory_budget(MemoryBudget::Chunks(2))
+            .build();
+
+        for x in 0..4 {
+            world.add_chunk(ChunkPosition::new(x, 0), None)?;
+        }
+
+        let empty_usage: usize = world.memory_usage();
+        world.set_sky_light(BlockPosition::new(1, 1, 1), 3)?;
+        assert!(world.memory_usage() > empty_usage);
+
+        world.pin_chunk(ChunkPosition::new(2, 0));
+        assert!(world.is_pinned(ChunkPosition::new(2, 0)));
+
+        // saving is no access, so chunk 1 stays the least recently used
+        world.save_chunk(ChunkPosition::new(1, 0)).await?;
+
+        let evicted: Vec<ChunkPosition> = world.evict().await?;
+        assert_eq!(
+            evicted,
+            vec![ChunkPosition::new(1, 0), ChunkPosition::new(3, 0)]
+        );
+        assert!(world.is_chunk_at_pos(ChunkPosition::new(0, 0)));
+        assert!(world.is_chunk_at_pos(ChunkPosition::new(2, 0)));
+        assert_eq!(storage.len(), 2);
+
+        assert!(world.evict().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evictor_runs_in_background() -> Result<(), ChunkStoreError> {
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(MemoryStorage::new())
+                .memory_budget(MemoryBudget::Chunks(1))
+                .build(),
+        );
+        let evictor = world.spawn_evictor();
+
+        world.add_chunk(ChunkPosition::new(0, 0), None)?;
+        world.add_chunk(ChunkPosition::new(1, 0), None)?;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while world.is_chunk_at_pos(ChunkPosition::new(0, 0)) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("evictor did not run");
+        assert!(world.is_chunk_at_pos(ChunkPosition::new(1, 0)));
+
+        drop(world);
+        evictor.await.unwrap();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evictor_follows_growing_chunks() -> Result<(), ChunkStoreError> {
+        let pos: BlockPosition = BlockPosition::new(17, 1, 1);
+        let empty_size: usize = Chunk::default().memory_size();
+        let mut grown: Chunk = Chunk::default();
+        grown.set_sky_light(World::global_to_local_pos(pos), 3)?;
+
+        // fits two empty chunks, but not an empty and a grown one
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(MemoryStorage::new())
+                .memory_budget(MemoryBudget::Bytes(empty_size + grown.memory_size() - 1))
+                .build(),
+        );
+        let evictor = world.spawn_evictor();
+
+        world.add_chunk(ChunkPosition::new(0, 0), None)?;
+        world.add_chunk(ChunkPosition::new(1, 0), None)?;
+        tokio::task::yield_now().await;
+        assert!(world.is_chunk_at_pos(ChunkPosition::new(0, 0)));
+
+        world.set_sky_light(pos, 3)?;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while world.is_chunk_at_pos(ChunkPosition::new(0, 0)) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("evictor did not run");
+        assert!(world.is_chunk_at_pos(ChunkPosition::new(1, 0)));
+
+        drop(world);
+        evictor.await.unwrap();
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_autosave_flushes_dirty_chunks() -> Result<(), ChunkStoreError> {
+        let storage: Arc<MemoryStorage> = Arc::new(MemoryStorage::new());
+        let world: Arc<World> = Arc::new(World::builder().storage(Arc::clone(&storage)).build());
+        let autosave: AutosaveHandle = Autosave::new(Arc::clone(&world))
+            .interval(Duration::from_secs(1))
+            .spawn();
+
+        world.add_chunk(ChunkPosition::ZERO, None)?;
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(storage.len(), 1);
+        assert!(world.dirty_chunks().is_empty());
+
+        world.add_chunk(ChunkPosition::new(1, 0), None)?;
+        let report: FlushReport = autosave.stop().await;
+        assert_eq!(report.saved, vec![ChunkPosition::new(1, 0)]);
+        assert!(report.errors.is_empty());
+        assert_eq!(storage.len(), 2);
+
+        Ok(())
+    }
+
+    /// Memory storage that refuses to write one chunk.
+    /// Only implements the blocking methods, which the async world API then runs.
+    struct FailingStorage {
+        inner: MemoryStorage,
+        failing: ChunkPosition,
+    }
+
+    impl ChunkStorage for FailingStorage {
+        fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+            self.inner.read_blocking(pos)
+        }
+
+        fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()> {
+            if pos == self.failing {
+                return Err(io::Error::other("disk full"));
+            }
+            self.inner.write_blocking(pos, data)
+        }
+
+        fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+            self.inner.delete_blocking(pos)
+        }
+
+        fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>> {
+            self.inner.list_blocking()
+        }
+
+        fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>> {
+            self.inner.read_metadata_blocking()
+        }
+
+        fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()> {
+            self.inner.write_metadata_blocking(data)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_all_and_shutdown() -> Result<(), ChunkStoreError> {
+        let failing: ChunkPosition = ChunkPosition::new(2, 2);
+        let storage: Arc<FailingStorage> = Arc::new(FailingStorage {
+            inner: MemoryStorage::new(),
+            failing,
+        });
+        let world: Arc<World> = Arc::new(
+            World::builder()
+                .storage(Arc::clone(&storage))
+                .io_concurrency(2)
+                .build(),
+        );
+
+        for chunk_pos in World::positions_in_square(ChunkPosition::new(1, 1), 1) {
+            world.add_chunk(chunk_pos, None)?;
         }
 
+        let error: SaveAllError = world.save_all().await.unwrap_err();
+        assert_eq!(error.failed.len(), 1);
+        assert_eq!(error.failed[0].0, failing);
+        assert_eq!(storage.inner.len(), 8);
+        assert_eq!(world.dirty_chunks(), vec![failing]);
+
+        let error: SaveAllError = world.shutdown().await.unwrap_err();
+        assert_eq!(error.failed.len(), 1);
+        assert!(world.is_chunk_at_pos(failing));
+        assert!(world.chunk(failing).unwrap().is_dirty());
+        assert!(!world.is_chunk_at_pos(ChunkPosition::ZERO));
+
+        assert_eq!(world.shutdown().await.unwrap_err().failed[0].0, failing);
+
         Ok(())
     }
 }
 
+#[cfg(all(test, feature = "persistence"))]
+mod blocking_tests {
+    use super::prelude::*;
+    use std::{fs, path::PathBuf};
+
+    world! {
+        chunk_width: 4,
+        chunk_height: 4,
+        subchunk_depth: 4,
+        num_subchunks: 2,
+        Block r#as block: u8 = 4,
+    }
+
+    mod lit {
+        crate::world! {
+            chunk_width: 4,
+            chunk_height: 4,
+            subchunk_depth: 4,
+            num_subchunks: 2,
+            Block r#as block: u8 = 4,
+            #[default = 15]
+            SkyLight r#as sky_light: u8 = 4,
+            #[default = -1]
+            Temperature r#as temperature: i8 = 3,
+        }
+    }
+
+    fn test_root(name: &str) -> PathBuf {
+        let root: PathBuf = std::env::temp_dir().join(format!("terrain_data_blocking_{name}"));
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        root
+    }
+
+    fn round_trip(world: &World) -> Result<(), ChunkStoreError> {
+        let chunk_positions: [ChunkPosition; 2] =
+            [ChunkPosition::new(0, 0), ChunkPosition::new(-3, 7)];
+
+        for (i, &chunk_pos) in chunk_positions.iter().enumerate() {
+            world.load_or_default_blocking(chunk_pos)?;
+            world.set_block(World::chunk_to_block_pos(chunk_pos), i as u8 + 1)?;
+        }
+
+        assert_eq!(world.save_all_dirty_blocking()?, 2);
+        assert!(world.dirty_chunks().is_empty());
+        world.set_block(World::chunk_to_block_pos(chunk_positions[0]), 9)?;
+        assert_eq!(world.shutdown_blocking().unwrap(), 2);
+        assert!(
+            world
+                .block(World::chunk_to_block_pos(chunk_positions[0]))
+                .is_err()
+        );
+
+        let mut stored_chunks: Vec<ChunkPosition> = world.stored_chunks_blocking()?;
+        stored_chunks.sort_by_key(|pos| (pos.x, pos.y));
+        assert_eq!(stored_chunks, [chunk_positions[1], chunk_positions[0]]);
+        assert_eq!(world.load_metadata_blocking()?, World::metadata());
+
+        world.load_chunk_blocking(chunk_positions[0])?;
+        world.load_or_default_blocking(chunk_positions[1])?;
+        assert_eq!(
+            world.block(World::chunk_to_block_pos(chunk_positions[0]))?,
+            9
+        );
+        assert_eq!(
+            world.block(World::chunk_to_block_pos(chunk_positions[1]))?,
+            2
+        );
+
+        assert!(matches!(
+            world.load_chunk_blocking(ChunkPosition::new(1, 1)),
+            Err(ChunkStoreError::NotStored(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_file_storage() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("file_storage");
+        round_trip(&World::new(&root))?;
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_region_storage() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("region_storage");
+        round_trip(
+            &World::builder()
+                .root(&root)
+                .format(StorageFormat::Regions)
+                .build(),
+        )?;
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_memory_storage() -> Result<(), ChunkStoreError> {
+        round_trip(&World::builder().storage(MemoryStorage::new()).build())
+    }
+
+    #[test]
+    fn test_concurrent_first_saves() -> Result<(), ChunkStoreError> {
+        let root: PathBuf = test_root("concurrent_first_saves");
+        let world: World = World::new(&root);
+        let chunk_positions: Vec<ChunkPosition> =
+            (0..8).map(|x| ChunkPosition::new(x, 0)).collect();
+
+        for &chunk_pos in &chunk_positions {
+            world.add_chunk(chunk_pos, None)?;
+        }
+
+        // every thread finds the metadata missing and races to write it
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunk_positions
+                .iter()
+                .map(|&chunk_pos| {
+                    let world: &World = &world;
+                    scope.spawn(move || world.save_chunk_blocking(chunk_pos))
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().unwrap())
+        })?;
+
+        assert_eq!(world.load_metadata_blocking()?, World::metadata());
+        assert_eq!(world.stored_chunks_blocking()?.len(), chunk_positions.len());
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_defaults() -> Result<(), ChunkStoreError> {
+        let pos: BlockPosition = BlockPosition::new(1, 2, 5);
+
+        let mut chunk: lit::Chunk = lit::Chunk::default();
+        let empty_size: usize = chunk.memory_size();
+        assert_eq!(chunk.sky_light(pos)?, 15);
+        assert_eq!(chunk.temperature(pos)?, -1);
+
+        chunk.set_sky_light(pos, 15)?;
+        assert_eq!(chunk.memory_size(), empty_size);
+        chunk.set_sky_light(pos, 0)?;
+        assert_eq!(chunk.sky_light(pos)?, 0);
+        assert_eq!(chunk.sky_light(BlockPosition::ZERO)?, 15);
+        chunk.set_sky_light(pos, 15)?;
+        assert_eq!(chunk.memory_size(), empty_size);
+
+        let world: lit::World = lit::World::builder().storage(MemoryStorage::new()).build();
+        world.load_or_default_blocking(ChunkPosition::ZERO)?;
+        world.set_sky_light(pos, 7)?;
+        world.set_temperature(pos, 2)?;
+        world.unload_chunk_blocking(ChunkPosition::ZERO)?;
+
+        world.load_chunk_blocking(ChunkPosition::ZERO)?;
+        assert_eq!(world.sky_light(pos)?, 7);
+        assert_eq!(world.temperature(pos)?, 2);
+        assert_eq!(world.sky_light(BlockPosition::ZERO)?, 15);
+        assert_eq!(world.temperature(BlockPosition::ZERO)?, -1);
+        Ok(())
+    }
+}
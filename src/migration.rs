@@ -0,0 +1,468 @@
+use crate::{
+    core::{BlockPosition, FieldInfo},
+    error::MigrationError,
+};
+use chroma::Section;
+
+/// Sections of one subchunk, indexed like the field list they were stored with.
+/// Values are stored relative to their field's default, and `None` marks a subchunk without any data.
+pub type SubchunkRecord<const W: usize, const H: usize, const D: usize> =
+    Option<Vec<Option<Section<W, H, D>>>>;
+
+/// A single change to the field list of a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationStep {
+    /// Adds a field, filling every block with the raw default value.
+    Add {
+        field: &'static str,
+        bits: u8,
+        default: u64,
+    },
+    /// Drops a field and all of its data.
+    Remove { field: &'static str },
+    /// Renames a field, keeping its data.
+    Rename {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// Grows the bit width of a field, keeping its data.
+    Widen { field: &'static str, bits: u8 },
+}
+
+/// Steps upgrading chunks saved with `from_version` to the next schema version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub from_version: u32,
+    pub steps: &'static [MigrationStep],
+}
+
+/// Builds a [`MigrationStep`] from the syntax accepted in `world!` migrations.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __migration_step {
+    (add($field:ident, bits: $bits:expr, default: $default:expr)) => {{
+        const _: () = assert!(
+            $bits >= 64 || ($default as u64) >> $bits == 0,
+            concat!(
+                "Default of added field ",
+                stringify!($field),
+                " does not fit its bits."
+            ),
+        );
+
+        $crate::migration::MigrationStep::Add {
+            field: stringify!($field),
+            bits: $bits,
+            default: $default,
+        }
+    }};
+    (remove($field:ident)) => {
+        $crate::migration::MigrationStep::Remove {
+            field: stringify!($field),
+        }
+    };
+    (rename($from:ident, $to:ident)) => {
+        $crate::migration::MigrationStep::Rename {
+            from: stringify!($from),
+            to: stringify!($to),
+        }
+    };
+    (widen($field:ident, bits: $bits:expr)) => {
+        $crate::migration::MigrationStep::Widen {
+            field: stringify!($field),
+            bits: $bits,
+        }
+    };
+}
+
+/// Applies every migration between the two versions in order,
+/// updating the field list alongside the subchunk data.
+pub fn migrate<const W: usize, const H: usize, const D: usize>(
+    fields: &mut Vec<FieldInfo>,
+    subchunks: &mut [SubchunkRecord<W, H, D>],
+    from_version: u32,
+    to_version: u32,
+    migrations: &[Migration],
+) -> Result<(), MigrationError> {
+    if from_version > to_version {
+        return Err(MigrationError::NewerVersion {
+            found: from_version,
+            current: to_version,
+        });
+    }
+
+    for version in from_version..to_version {
+        let migration: &Migration = migrations
+            .iter()
+            .find(|migration| migration.from_version == version)
+            .ok_or(MigrationError::MissingMigration(version))?;
+
+        for step in migration.steps {
+            apply_step(fields, subchunks, step)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reorders the sections of every subchunk to match the target field list,
+/// rebasing the data of fields whose default changed.
+/// Fails if the fields do not line up exactly with the target by name and bit width.
+pub fn arrange<const W: usize, const H: usize, const D: usize>(
+    fields: &[FieldInfo],
+    subchunks: &mut [SubchunkRecord<W, H, D>],
+    target: &[FieldInfo],
+) -> Result<(), MigrationError> {
+    check_sections(fields, subchunks)?;
+
+    if let Some(duplicate) = fields.iter().enumerate().find_map(|(index, field)| {
+        fields[..index]
+            .iter()
+            .any(|other| other.name == field.name)
+            .then_some(field)
+    }) {
+        return Err(MigrationError::DuplicateField(duplicate.name.clone()));
+    }
+
+    if let Some(extra) = fields
+        .iter()
+        .find(|field| !target.iter().any(|expected| expected.name == field.name))
+    {
+        return Err(MigrationError::UnexpectedField(extra.name.clone()));
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(target.len());
+
+    for expected in target {
+        let index: usize = field_index(fields, &expected.name)?;
+
+        if fields[index].bits != expected.bits {
+            return Err(MigrationError::BitsMismatch {
+                field: expected.name.clone(),
+                expected: expected.bits,
+                found: fields[index].bits,
+            });
+        }
+
+        order.push(index);
+    }
+
+    let flips: Vec<u64> = order
+        .iter()
+        .zip(target)
+        .map(|(&index, expected)| fields[index].default ^ expected.default)
+        .collect();
+    let rebased: bool = flips.iter().any(|&flip| flip != 0);
+
+    for subchunk in subchunks.iter_mut() {
+        let mut arranged: Vec<Option<Section<W, H, D>>> = match subchunk {
+            Some(sections) => order.iter().map(|&index| sections[index].take()).collect(),
+            None if rebased => order.iter().map(|_| None).collect(),
+            None => continue,
+        };
+
+        for ((section, &flip), expected) in arranged.iter_mut().zip(&flips).zip(target) {
+            rebase(section, expected.bits, flip)?;
+        }
+
+        *subchunk = arranged.iter().any(Option::is_some).then_some(arranged);
+    }
+
+    Ok(())
+}
+
+fn apply_step<const W: usize, const H: usize, const D: usize>(
+    fields: &mut Vec<FieldInfo>,
+    subchunks: &mut [SubchunkRecord<W, H, D>],
+    step: &MigrationStep,
+) -> Result<(), MigrationError> {
+    check_sections(fields, subchunks)?;
+
+    match *step {
+        MigrationStep::Add {
+            field,
+            bits,
+            default,
+        } => {
+            if field_index(fields, field).is_ok() {
+                return Err(MigrationError::DuplicateField(field.to_string()));
+            }
+
+            if bits < 64 && default >> bits != 0 {
+                return Err(MigrationError::DefaultOutOfRange {
+                    field: field.to_string(),
+                    default,
+                    bits,
+                });
+            }
+
+            fields.push(FieldInfo {
+                name: field.to_string(),
+                bits,
+                default,
+            });
+
+            // values are stored relative to the default, so a field filled with it needs no sections
+            for sections in subchunks.iter_mut().flatten() {
+                sections.push(None);
+            }
+        }
+        MigrationStep::Remove { field } => {
+            let index: usize = field_index(fields, field)?;
+            fields.remove(index);
+
+            for sections in subchunks.iter_mut().flatten() {
+                sections.remove(index);
+            }
+        }
+        MigrationStep::Rename { from, to } => {
+            if field_index(fields, to).is_ok() {
+                return Err(MigrationError::DuplicateField(to.to_string()));
+            }
+
+            let index: usize = field_index(fields, from)?;
+            fields[index].name = to.to_string();
+        }
+        MigrationStep::Widen { field, bits } => {
+            let index: usize = field_index(fields, field)?;
+            let old_bits: u8 = fields[index].bits;
+
+            if bits < old_bits {
+                return Err(MigrationError::Narrowing {
+                    field: field.to_string(),
+                    from: old_bits,
+                    to: bits,
+                });
+            }
+
+            fields[index].bits = bits;
+
+            for sections in subchunks.iter_mut().flatten() {
+                let Some(old) = sections[index].take() else {
+                    continue;
+                };
+
+                let mut section: Section<W, H, D> = Section::new(bits);
+
+                for pos in positions::<W, H, D>() {
+                    let value: u64 = old.item(pos)?;
+                    if value != 0 {
+                        section.set_item(pos, value)?;
+                    }
+                }
+
+                sections[index] = Some(section);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Flips the passed bits of every value, so the section is stored relative to a different default.
+fn rebase<const W: usize, const H: usize, const D: usize>(
+    section: &mut Option<Section<W, H, D>>,
+    bits: u8,
+    flip: u64,
+) -> Result<(), MigrationError> {
+    if flip == 0 {
+        return Ok(());
+    }
+
+    let mut rebased: Section<W, H, D> = Section::new(bits);
+
+    for pos in positions::<W, H, D>() {
+        let value: u64 = section.as_ref().map_or(Ok(0), |s| s.item(pos))?;
+        rebased.set_item(pos, value ^ flip)?;
+    }
+
+    *section = (!rebased.is_empty()).then_some(rebased);
+    Ok(())
+}
+
+/// Fails unless every subchunk holds exactly one section per field, so indexing them cannot panic.
+fn check_sections<const W: usize, const H: usize, const D: usize>(
+    fields: &[FieldInfo],
+    subchunks: &[SubchunkRecord<W, H, D>],
+) -> Result<(), MigrationError> {
+    match subchunks
+        .iter()
+        .flatten()
+        .find(|sections| sections.len() != fields.len())
+    {
+        Some(sections) => Err(MigrationError::SectionCount {
+            expected: fields.len(),
+            found: sections.len(),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn field_index(fields: &[FieldInfo], name: &str) -> Result<usize, MigrationError> {
+    fields
+        .iter()
+        .position(|field| field.name == name)
+        .ok_or_else(|| MigrationError::UnknownField(name.to_string()))
+}
+
+fn positions<const W: usize, const H: usize, const D: usize>() -> impl Iterator<Item = BlockPosition>
+{
+    (0..W as i32).flat_map(|x| {
+        (0..H as i32).flat_map(move |y| (0..D as i32).map(move |z| BlockPosition::new(x, y, z)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Record = SubchunkRecord<2, 2, 2>;
+
+    fn fields(list: &[(&str, u8)]) -> Vec<FieldInfo> {
+        list.iter()
+            .map(|&(name, bits)| FieldInfo {
+                name: name.to_string(),
+                bits,
+                default: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_arrange_rebases_changed_defaults() -> Result<(), MigrationError> {
+        let pos: BlockPosition = BlockPosition::new(1, 0, 1);
+        let mut section: Section<2, 2, 2> = Section::new(4);
+        section.set_item(pos, 3)?;
+
+        let mut subchunks: Vec<Record> = vec![Some(vec![Some(section)]), None];
+        let mut lit: Vec<FieldInfo> = fields(&[("Light", 4)]);
+        lit[0].default = 15;
+
+        arrange(&fields(&[("Light", 4)]), &mut subchunks, &lit)?;
+
+        let rebased: &Section<2, 2, 2> = subchunks[0].as_ref().unwrap()[0].as_ref().unwrap();
+        assert_eq!(rebased.item(pos)? ^ 15, 3);
+        assert_eq!(rebased.item(BlockPosition::ZERO)? ^ 15, 0);
+        assert!(subchunks[1].is_some());
+
+        arrange(&lit, &mut subchunks, &fields(&[("Light", 4)]))?;
+        assert!(subchunks[1].is_none());
+        assert_eq!(
+            subchunks[0].as_ref().unwrap()[0]
+                .as_ref()
+                .unwrap()
+                .item(pos)?,
+            3
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_migrations_are_rejected() {
+        const NARROW: &[Migration] = &[Migration {
+            from_version: 0,
+            steps: &[MigrationStep::Widen {
+                field: "Block",
+                bits: 2,
+            }],
+        }];
+
+        let mut subchunks: Vec<Record> = vec![None];
+
+        assert!(matches!(
+            migrate(&mut fields(&[("Block", 4)]), &mut subchunks, 0, 1, NARROW),
+            Err(MigrationError::Narrowing { from: 4, to: 2, .. })
+        ));
+        assert!(matches!(
+            migrate(&mut fields(&[("Block", 4)]), &mut subchunks, 1, 2, NARROW),
+            Err(MigrationError::MissingMigration(1))
+        ));
+        assert!(matches!(
+            arrange(
+                &fields(&[("Block", 4), ("Light", 4)]),
+                &mut subchunks,
+                &fields(&[("Block", 4)])
+            ),
+            Err(MigrationError::UnexpectedField(field)) if field == "Light"
+        ));
+        assert!(matches!(
+            arrange(
+                &fields(&[("Block", 4)]),
+                &mut subchunks,
+                &fields(&[("Block", 8)])
+            ),
+            Err(MigrationError::BitsMismatch {
+                expected: 8,
+                found: 4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_sections_are_rejected() {
+        const REMOVE: &[Migration] = &[Migration {
+            from_version: 0,
+            steps: &[MigrationStep::Remove { field: "Light" }],
+        }];
+
+        let mut subchunks: Vec<Record> = vec![Some(vec![None])];
+
+        assert!(matches!(
+            migrate(
+                &mut fields(&[("Block", 4), ("Light", 4)]),
+                &mut subchunks,
+                0,
+                1,
+                REMOVE
+            ),
+            Err(MigrationError::SectionCount {
+                expected: 2,
+                found: 1
+            })
+        ));
+        assert!(matches!(
+            arrange(
+                &fields(&[("Block", 4), ("Light", 4)]),
+                &mut subchunks,
+                &fields(&[("Block", 4), ("Light", 4)])
+            ),
+            Err(MigrationError::SectionCount { .. })
+        ));
+
+        let mut subchunks: Vec<Record> = vec![Some(vec![None, None])];
+        assert!(matches!(
+            arrange(
+                &fields(&[("Block", 4), ("Block", 4)]),
+                &mut subchunks,
+                &fields(&[("Block", 4)])
+            ),
+            Err(MigrationError::DuplicateField(field)) if field == "Block"
+        ));
+    }
+
+    #[test]
+    fn test_added_default_must_fit() {
+        const ADD: &[Migration] = &[Migration {
+            from_version: 0,
+            steps: &[MigrationStep::Add {
+                field: "Exposed",
+                bits: 1,
+                default: 5,
+            }],
+        }];
+
+        let mut fields: Vec<FieldInfo> = fields(&[("Block", 4)]);
+        let mut subchunks: Vec<Record> = vec![Some(vec![None])];
+
+        assert!(matches!(
+            migrate(&mut fields, &mut subchunks, 0, 1, ADD),
+            Err(MigrationError::DefaultOutOfRange {
+                default: 5,
+                bits: 1,
+                ..
+            })
+        ));
+        assert_eq!(fields.len(), 1);
+    }
+}
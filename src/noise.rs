@@ -0,0 +1,416 @@
+//! Deterministic, seeded noise for terrain generation.
+//!
+//! Output only depends on the seed and the sampled coordinates,
+//! and stays identical across platforms and crate versions.
+
+/// A seeded noise function returning values roughly within `-1.0..=1.0`.
+pub trait Noise: Send + Sync {
+    fn sample2(&self, x: f64, y: f64) -> f64;
+
+    fn sample3(&self, x: f64, y: f64, z: f64) -> f64;
+}
+
+// -- Perlin --
+
+/// Gradient noise using Ken Perlin's improved algorithm with a seeded permutation table.
+#[derive(Clone)]
+pub struct Perlin {
+    seed: u64,
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+        let mut state: u64 = seed;
+
+        // Fisher-Yates shuffle
+        for i in (1..table.len()).rev() {
+            let j: usize = (split_mix64(&mut state) % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        Self {
+            seed,
+            perm: std::array::from_fn(|i| table[i & 255]),
+        }
+    }
+
+    /// Returns the seed the permutation table was shuffled with.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    fn hash(&self, i: usize) -> usize {
+        self.perm[i] as usize
+    }
+}
+
+impl Noise for Perlin {
+    fn sample2(&self, x: f64, y: f64) -> f64 {
+        let (xi, x) = lattice(x);
+        let (yi, y) = lattice(y);
+        let (u, v) = (fade(x), fade(y));
+
+        let a: usize = self.hash(xi) + yi;
+        let b: usize = self.hash(xi + 1) + yi;
+
+        lerp(
+            v,
+            lerp(
+                u,
+                grad2(self.hash(a), x, y),
+                grad2(self.hash(b), x - 1.0, y),
+            ),
+            lerp(
+                u,
+                grad2(self.hash(a + 1), x, y - 1.0),
+                grad2(self.hash(b + 1), x - 1.0, y - 1.0),
+            ),
+        )
+    }
+
+    fn sample3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (xi, x) = lattice(x);
+        let (yi, y) = lattice(y);
+        let (zi, z) = lattice(z);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a: usize = self.hash(xi) + yi;
+        let aa: usize = self.hash(a) + zi;
+        let ab: usize = self.hash(a + 1) + zi;
+        let b: usize = self.hash(xi + 1) + yi;
+        let ba: usize = self.hash(b) + zi;
+        let bb: usize = self.hash(b + 1) + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad3(self.hash(aa), x, y, z),
+                    grad3(self.hash(ba), x - 1.0, y, z),
+                ),
+                lerp(
+                    u,
+                    grad3(self.hash(ab), x, y - 1.0, z),
+                    grad3(self.hash(bb), x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad3(self.hash(aa + 1), x, y, z - 1.0),
+                    grad3(self.hash(ba + 1), x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad3(self.hash(ab + 1), x, y - 1.0, z - 1.0),
+                    grad3(self.hash(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+// -- Fbm --
+
+/// Fractal Brownian motion, summing octaves of a source noise at rising frequency and falling amplitude.
+/// The sum is normalized so it keeps the range of the source.
+#[derive(Clone)]
+pub struct Fbm<N> {
+    source: N,
+    octaves: u32,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl<N: Noise> Fbm<N> {
+    /// Creates fractal noise with 4 octaves, doubling frequency and halving amplitude each octave.
+    pub fn new(source: N) -> Self {
+        Self {
+            source,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+
+    /// Sets the number of summed octaves.
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Sets the frequency of the first octave.
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Sets the factor the frequency grows by each octave.
+    pub fn lacunarity(mut self, lacunarity: f64) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    /// Sets the factor the amplitude shrinks by each octave.
+    pub fn persistence(mut self, persistence: f64) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    fn sum(&self, sample: impl Fn(f64, f64) -> f64) -> f64 {
+        let mut total: f64 = 0.0;
+        let mut max: f64 = 0.0;
+        let mut frequency: f64 = self.frequency;
+        let mut amplitude: f64 = 1.0;
+
+        for octave in 0..self.octaves {
+            // shifts every octave so they do not all vanish at the origin
+            total += sample(frequency, octave as f64 * OCTAVE_OFFSET) * amplitude;
+            max += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+
+        if max == 0.0 { 0.0 } else { total / max }
+    }
+}
+
+const OCTAVE_OFFSET: f64 = 71.37;
+
+impl<N: Noise> Noise for Fbm<N> {
+    fn sample2(&self, x: f64, y: f64) -> f64 {
+        self.sum(|frequency, offset| {
+            self.source
+                .sample2(x * frequency + offset, y * frequency + offset)
+        })
+    }
+
+    fn sample3(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.sum(|frequency, offset| {
+            self.source.sample3(
+                x * frequency + offset,
+                y * frequency + offset,
+                z * frequency + offset,
+            )
+        })
+    }
+}
+
+// -- DomainWarp --
+
+/// Offsets the coordinates passed to a source noise by a second warp noise,
+/// turning regular features into twisted, more natural ones.
+#[derive(Clone)]
+pub struct DomainWarp<N, W> {
+    source: N,
+    warp: W,
+    strength: f64,
+}
+
+impl<N: Noise, W: Noise> DomainWarp<N, W> {
+    /// Creates a warp moving coordinates by up to `strength` units along each axis.
+    pub fn new(source: N, warp: W, strength: f64) -> Self {
+        Self {
+            source,
+            warp,
+            strength,
+        }
+    }
+}
+
+/// Offsets sampling the warp once per axis, so the axes are displaced independently.
+const WARP_OFFSETS: [f64; 3] = [0.0, 31.71, 57.13];
+
+impl<N: Noise, W: Noise> Noise for DomainWarp<N, W> {
+    fn sample2(&self, x: f64, y: f64) -> f64 {
+        let [dx, dy, _] = WARP_OFFSETS.map(|offset| self.warp.sample2(x + offset, y + offset));
+        self.source
+            .sample2(x + dx * self.strength, y + dy * self.strength)
+    }
+
+    fn sample3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let [dx, dy, dz] =
+            WARP_OFFSETS.map(|offset| self.warp.sample3(x + offset, y + offset, z + offset));
+        self.source.sample3(
+            x + dx * self.strength,
+            y + dy * self.strength,
+            z + dz * self.strength,
+        )
+    }
+}
+
+// -- helpers --
+
+/// Advances the state and returns the next SplitMix64 output.
+fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z: u64 = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Splits a coordinate into its wrapped lattice cell and the offset within it.
+#[inline]
+fn lattice(value: f64) -> (usize, f64) {
+    let floor: f64 = value.floor();
+    ((floor as i64 & 255) as usize, value - floor)
+}
+
+#[inline]
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+#[inline]
+fn grad2(hash: usize, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[inline]
+fn grad3(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    match hash & 15 {
+        0 | 12 => x + y,
+        1 | 14 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 | 13 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [(f64, f64, f64); 4] = [
+        (0.5, 0.25, 0.75),
+        (12.3, -4.7, 1.1),
+        (-101.9, 33.3, -7.5),
+        (1e4 + 0.37, 2e4 + 0.61, -3e4 - 0.19),
+    ];
+
+    fn assert_golden(values: impl IntoIterator<Item = f64>, expected: &[f64]) {
+        let values: Vec<f64> = values.into_iter().collect();
+        assert_eq!(values.len(), expected.len());
+
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-12,
+                "{values:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_golden_values() {
+        let perlin: Perlin = Perlin::new(42);
+        let fbm: Fbm<Perlin> = Fbm::new(Perlin::new(7)).octaves(5).frequency(0.05);
+        let warped = DomainWarp::new(Perlin::new(1), Fbm::new(Perlin::new(2)), 4.0);
+
+        assert_golden(
+            POINTS.map(|(x, y, _)| perlin.sample2(x, y)),
+            &[
+                -0.086181640625,
+                0.3056699654400006,
+                -0.040901175360001735,
+                -0.41865831868366854,
+            ],
+        );
+        assert_golden(
+            POINTS.map(|(x, y, z)| perlin.sample3(x, y, z)),
+            &[
+                0.3255448341369629,
+                -0.11951550911999913,
+                -0.2513551929599998,
+                0.19243733066598115,
+            ],
+        );
+        assert_golden(
+            POINTS.map(|(x, y, _)| fbm.sample2(x, y)),
+            &[
+                -0.11455032004122102,
+                0.191470289798107,
+                -0.13178121205391025,
+                -0.043423280284528916,
+            ],
+        );
+        assert_golden(
+            POINTS.map(|(x, y, z)| fbm.sample3(x, y, z)),
+            &[
+                -0.15032865204607704,
+                -0.10636306803203457,
+                -0.03962249173074364,
+                -0.16026906465189603,
+            ],
+        );
+        assert_golden(
+            POINTS.map(|(x, y, _)| warped.sample2(x, y)),
+            &[
+                0.16227079161610225,
+                -0.24086557011043966,
+                -0.08864296768055036,
+                0.5260087929875132,
+            ],
+        );
+        assert_golden(
+            POINTS.map(|(x, y, z)| warped.sample3(x, y, z)),
+            &[
+                0.5364523398744805,
+                -0.006825512874014339,
+                0.11185484805797863,
+                -0.1898252979854107,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_seeds_and_range() {
+        let a: Perlin = Perlin::new(1);
+        let b: Perlin = Perlin::new(2);
+
+        assert_eq!(a.sample2(3.0, 4.0), 0.0);
+        assert_ne!(a.sample2(3.3, 4.6), b.sample2(3.3, 4.6));
+        assert_eq!(
+            a.sample3(3.3, 4.6, 5.9),
+            Perlin::new(1).sample3(3.3, 4.6, 5.9)
+        );
+
+        let fbm: Fbm<Perlin> = Fbm::new(a).octaves(6);
+
+        for i in 0..1000 {
+            let t: f64 = i as f64 * 0.173;
+            assert!(fbm.sample2(t, -t * 0.5).abs() <= 1.0);
+            assert!(fbm.sample3(t, t * 0.3, -t).abs() <= 1.0);
+        }
+    }
+}
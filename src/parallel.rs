@@ -0,0 +1,54 @@
+use crate::{core::ChunkPosition, error::ChunkStoreError};
+use std::{collections::HashMap, future::Future, io, panic, sync::Arc};
+use tokio::task::{self, JoinSet};
+
+/// Runs the operation for every position as tokio tasks, starting them in order
+/// while keeping at most `max_concurrent` running. Results are returned in start order.
+pub async fn run_limited<W, T, F, Fut>(
+    world: &Arc<W>,
+    max_concurrent: usize,
+    positions: Vec<ChunkPosition>,
+    operation: F,
+) -> Vec<(ChunkPosition, Result<T, ChunkStoreError>)>
+where
+    T: Send + 'static,
+    F: Fn(Arc<W>, ChunkPosition) -> Fut,
+    Fut: Future<Output = Result<T, ChunkStoreError>> + Send + 'static,
+{
+    let mut results: Vec<Option<Result<T, ChunkStoreError>>> =
+        positions.iter().map(|_| None).collect();
+    let mut indices: HashMap<task::Id, usize> = HashMap::with_capacity(positions.len());
+    let mut tasks: JoinSet<Result<T, ChunkStoreError>> = JoinSet::new();
+    let mut pending = positions.iter().copied().enumerate();
+
+    loop {
+        while tasks.len() < max_concurrent {
+            let Some((index, pos)) = pending.next() else {
+                break;
+            };
+
+            let task = tasks.spawn(operation(Arc::clone(world), pos));
+            indices.insert(task.id(), index);
+        }
+
+        let Some(joined) = tasks.join_next_with_id().await else {
+            break;
+        };
+
+        match joined {
+            Ok((id, result)) => results[indices[&id]] = Some(result),
+            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+            // cancelled, which only happens while the runtime shuts down
+            Err(e) => {
+                let index: usize = indices[&e.id()];
+                results[index] = Some(Err(io::Error::other(e).into()));
+            }
+        }
+    }
+
+    positions
+        .into_iter()
+        .zip(results)
+        .map(|(pos, result)| (pos, result.expect("every task is joined")))
+        .collect()
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "persistence")]
+use crate::{
+    compression::Compression,
+    core::{CHUNKS_DIR, CorruptionPolicy, StorageFormat},
+    error::ChunkStoreError,
+    region::RegionStorage,
+    storage::{ChunkStorage, FileStorage},
+};
+#[cfg(feature = "persistence")]
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, atomic::AtomicBool},
+};
+
+/// Storage settings collected by a world builder.
+/// Lives outside `world!` so its fields follow the features of this crate. Empty without `persistence`.
+pub struct PersistenceOptions {
+    #[cfg(feature = "persistence")]
+    pub root: PathBuf,
+    #[cfg(feature = "persistence")]
+    pub format: StorageFormat,
+    #[cfg(feature = "persistence")]
+    pub storage: Option<Arc<dyn ChunkStorage>>,
+    #[cfg(feature = "persistence")]
+    pub rewrite_migrated: bool,
+    #[cfg(feature = "persistence")]
+    pub compression: Compression,
+    #[cfg(feature = "persistence")]
+    pub corruption_policy: CorruptionPolicy,
+    #[cfg(feature = "persistence")]
+    pub io_concurrency: usize,
+}
+
+// only derivable while the struct is empty
+#[cfg_attr(not(feature = "persistence"), allow(clippy::derivable_impls))]
+impl Default for PersistenceOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "persistence")]
+            root: PathBuf::from(CHUNKS_DIR),
+            #[cfg(feature = "persistence")]
+            format: StorageFormat::default(),
+            #[cfg(feature = "persistence")]
+            storage: None,
+            #[cfg(feature = "persistence")]
+            rewrite_migrated: false,
+            #[cfg(feature = "persistence")]
+            compression: Compression::default(),
+            #[cfg(feature = "persistence")]
+            corruption_policy: CorruptionPolicy::default(),
+            #[cfg(feature = "persistence")]
+            io_concurrency: 16,
+        }
+    }
+}
+
+impl PersistenceOptions {
+    /// Opens the built-in storage for the configured root and format, unless a custom one was set.
+    pub fn build(self) -> Persistence {
+        #[cfg(feature = "persistence")]
+        let storage: Arc<dyn ChunkStorage> = self.storage.unwrap_or_else(|| match self.format {
+            StorageFormat::ChunkFiles => Arc::new(FileStorage::new(self.root)),
+            StorageFormat::Regions => Arc::new(RegionStorage::new(self.root)),
+        });
+
+        Persistence {
+            #[cfg(feature = "persistence")]
+            storage,
+            #[cfg(feature = "persistence")]
+            metadata_saved: AtomicBool::new(false),
+            #[cfg(feature = "persistence")]
+            metadata_init: Mutex::new(()),
+            #[cfg(feature = "async-io")]
+            metadata_init_async: tokio::sync::Mutex::new(()),
+            #[cfg(feature = "persistence")]
+            rewrite_migrated: self.rewrite_migrated,
+            #[cfg(feature = "persistence")]
+            compression: self.compression,
+            #[cfg(feature = "persistence")]
+            corruption_policy: self.corruption_policy,
+            #[cfg(feature = "persistence")]
+            io_concurrency: self.io_concurrency,
+        }
+    }
+}
+
+/// Storage backend and settings of a world. Empty without `persistence`.
+pub struct Persistence {
+    #[cfg(feature = "persistence")]
+    pub storage: Arc<dyn ChunkStorage>,
+    /// Set once the world metadata is known to be in storage.
+    #[cfg(feature = "persistence")]
+    pub metadata_saved: AtomicBool,
+    /// Held by the blocking API while checking for and writing the metadata,
+    /// so concurrent first saves write it only once.
+    #[cfg(feature = "persistence")]
+    pub metadata_init: Mutex<()>,
+    /// Async counterpart of `metadata_init`, which must not be held across awaits.
+    #[cfg(feature = "async-io")]
+    pub metadata_init_async: tokio::sync::Mutex<()>,
+    #[cfg(feature = "persistence")]
+    pub rewrite_migrated: bool,
+    #[cfg(feature = "persistence")]
+    pub compression: Compression,
+    #[cfg(feature = "persistence")]
+    pub corruption_policy: CorruptionPolicy,
+    #[cfg(feature = "persistence")]
+    pub io_concurrency: usize,
+}
+
+/// What a load does with the data read for a chunk, decided before touching storage again.
+#[cfg(feature = "persistence")]
+pub enum StoredChunk<C> {
+    /// Decoded, alongside true if it was migrated from an older schema version.
+    Decoded(C, bool),
+    /// Never saved.
+    Missing,
+    /// Corrupted, and quarantined before the error is returned.
+    Quarantine(ChunkStoreError),
+    /// Corrupted, and replaced by a freshly created chunk.
+    Regenerate,
+}
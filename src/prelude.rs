@@ -1,4 +1,27 @@
-pub use crate::core::{BlockPosition, CHUNKS_DIR, ChunkPosition};
-pub use crate::error::{AccessError, ChunkAccessError, ChunkOverwriteError, ChunkStoreError};
+#[cfg(feature = "async-io")]
+pub use crate::autosave::{Autosave, AutosaveHandle, ChunkSaver, FlushReport};
+#[cfg(feature = "persistence")]
+pub use crate::compression::Compression;
+pub use crate::core::{
+    BlockPosition, CHUNKS_DIR, ChunkPosition, ChunkState, CorruptionPolicy, FieldType,
+    MemoryBudget, StorageFormat, WorldMetadata,
+};
+pub use crate::error::{AccessError, ChunkAccessError, ChunkOverwriteError};
+#[cfg(feature = "persistence")]
+pub use crate::error::{
+    ChunkStoreError, CompressionError, CorruptionError, HeaderError, MigrationError, SaveAllError,
+};
+#[cfg(feature = "persistence")]
+pub use crate::format::ChunkSchema;
+pub use crate::generator::{
+    ChunkGenerator, ChunkShape, FULLY_GENERATED, GenerationStage, HeightmapGenerator,
+};
+pub use crate::noise::{DomainWarp, Fbm, Noise, Perlin};
+#[cfg(feature = "persistence")]
+pub use crate::region::RegionStorage;
+#[cfg(feature = "persistence")]
+pub use crate::storage::{ChunkStorage, FileStorage, MemoryStorage};
+#[cfg(feature = "async-io")]
+pub use crate::tickets::{ChunkLoader, ChunkTicketManager, TickReport, ViewShape, ViewerId};
 pub use crate::world;
 pub use chroma::BoundsError;
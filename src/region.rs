@@ -0,0 +1,737 @@
+#[cfg(feature = "async-io")]
+use crate::storage::{BoxFuture, write_atomic};
+use crate::{
+    core::{ChunkPosition, METADATA_FILE},
+    error::CorruptionError,
+    storage::{ChunkStorage, not_found_as_none, write_atomic_blocking},
+};
+use glam::IVec2;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+#[cfg(feature = "async-io")]
+use tokio::task;
+
+/// Stores the two dimensional integer position of a region.
+pub type RegionPosition = IVec2;
+
+/// Number of chunks along each side of a region.
+pub const REGION_WIDTH: i32 = 32;
+
+/// Number of chunks a single region file can hold.
+pub const REGION_CHUNKS: usize = (REGION_WIDTH * REGION_WIDTH) as usize;
+
+/// Size in bytes of the allocation unit inside a region file.
+pub const SECTOR_SIZE: u64 = 4096;
+
+/// Number of region files a [`RegionStore`] keeps open by default.
+pub const MAX_OPEN_REGIONS: usize = 64;
+
+const ENTRY_SIZE: usize = 8;
+const HEADER_SIZE: usize = REGION_CHUNKS * ENTRY_SIZE;
+const HEADER_SECTORS: u32 = (HEADER_SIZE as u64).div_ceil(SECTOR_SIZE) as u32;
+
+/// Location of a chunk blob inside a region file.
+/// A length of zero marks an empty slot.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+struct Entry {
+    sector: u32,
+    len: u32,
+}
+
+impl Entry {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    fn sectors(&self) -> u32 {
+        sectors_for(self.len as usize)
+    }
+}
+
+#[inline]
+fn sectors_for(len: usize) -> u32 {
+    (len as u64).div_ceil(SECTOR_SIZE) as u32
+}
+
+/// Gets the region position a chunk position falls into.
+#[inline]
+pub const fn chunk_to_region_pos(pos: ChunkPosition) -> RegionPosition {
+    RegionPosition::new(
+        pos.x.div_euclid(REGION_WIDTH),
+        pos.y.div_euclid(REGION_WIDTH),
+    )
+}
+
+/// Gets the index of a chunk inside its region's header table.
+#[inline]
+const fn local_index(pos: ChunkPosition) -> usize {
+    (pos.x.rem_euclid(REGION_WIDTH) + pos.y.rem_euclid(REGION_WIDTH) * REGION_WIDTH) as usize
+}
+
+/// A single region file holding up to [`REGION_CHUNKS`] chunk blobs.
+///
+/// The file starts with a header table of sector offsets and byte lengths,
+/// followed by the blobs themselves aligned to [`SECTOR_SIZE`].
+pub struct RegionFile {
+    file: File,
+    entries: Box<[Entry; REGION_CHUNKS]>,
+}
+
+impl RegionFile {
+    /// Opens the region file at the passed path, creating an empty one if it is missing.
+    /// New files are written atomically, so an interrupted creation never leaves a partial header behind.
+    /// Files ending inside their header fail with a [`CorruptionError`] wrapped in an [`io::Error`].
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path: &Path = path.as_ref();
+
+        if !path.exists() {
+            write_atomic_blocking(path, &[0; HEADER_SECTORS as usize * SECTOR_SIZE as usize])?;
+        }
+
+        let mut file: File = OpenOptions::new().read(true).write(true).open(path)?;
+        let len: u64 = file.metadata()?.len();
+
+        if len < HEADER_SIZE as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                CorruptionError::RegionHeader { len },
+            ));
+        }
+
+        let mut entries: Box<[Entry; REGION_CHUNKS]> = Box::new([Entry::default(); REGION_CHUNKS]);
+        let mut header: Vec<u8> = vec![0; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+
+        for (entry, bytes) in entries.iter_mut().zip(header.chunks_exact(ENTRY_SIZE)) {
+            entry.sector = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+            entry.len = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        }
+
+        Ok(Self { file, entries })
+    }
+
+    /// Returns the blob stored for the passed chunk, if any.
+    /// Entries pointing outside the file fail with a [`CorruptionError`] wrapped in an [`io::Error`].
+    pub fn read(&mut self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+        let entry: Entry = self.entries[local_index(pos)];
+
+        if entry.is_empty() {
+            return Ok(None);
+        }
+
+        let start: u64 = entry.sector as u64 * SECTOR_SIZE;
+        let corrupted = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                CorruptionError::RegionEntry {
+                    sector: entry.sector,
+                    len: entry.len,
+                },
+            )
+        };
+
+        // checked before allocating, as a damaged header can claim any length
+        if entry.sector < HEADER_SECTORS || start + entry.len as u64 > self.file.metadata()?.len() {
+            return Err(corrupted());
+        }
+
+        let mut data: Vec<u8> = vec![0; entry.len as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file
+            .read_exact(&mut data)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => corrupted(),
+                _ => e,
+            })?;
+
+        Ok(Some(data))
+    }
+
+    /// Stores the blob for the passed chunk in the first free run of sectors large enough.
+    /// The chunk's current sectors are never overwritten, so a crash keeps the previous copy intact.
+    /// They become free once the header points at the new copy.
+    pub fn write(&mut self, pos: ChunkPosition, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return self.delete(pos);
+        }
+
+        let len: u32 = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "chunk blob too large"))?;
+        let index: usize = local_index(pos);
+        let needed: u32 = sectors_for(data.len());
+        let sector: u32 = self.allocate(needed);
+
+        // write and sync the blob before pointing the header at it so the old copy stays valid until then
+        self.file
+            .seek(SeekFrom::Start(sector as u64 * SECTOR_SIZE))?;
+        self.file.write_all(data)?;
+
+        let padding: usize = (needed as u64 * SECTOR_SIZE) as usize - data.len();
+        self.file.write_all(&vec![0; padding])?;
+        self.file.sync_data()?;
+
+        self.set_entry(index, Entry { sector, len })
+    }
+
+    /// Removes the blob for the passed chunk, freeing its sectors for reuse.
+    pub fn delete(&mut self, pos: ChunkPosition) -> io::Result<()> {
+        let index: usize = local_index(pos);
+
+        if self.entries[index].is_empty() {
+            return Ok(());
+        }
+
+        self.set_entry(index, Entry::default())
+    }
+
+    /// Returns true if a blob is stored for the passed chunk.
+    pub fn contains(&self, pos: ChunkPosition) -> bool {
+        !self.entries[local_index(pos)].is_empty()
+    }
+
+    /// Returns all chunk positions stored in this file, given the file's region position.
+    pub fn positions(&self, region: RegionPosition) -> impl Iterator<Item = ChunkPosition> + '_ {
+        let base: ChunkPosition = region * REGION_WIDTH;
+
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_empty())
+            .map(move |(i, _)| {
+                base + ChunkPosition::new(i as i32 % REGION_WIDTH, i as i32 / REGION_WIDTH)
+            })
+    }
+
+    /// Finds the first run of free sectors large enough for the request.
+    /// Sectors still referenced by the header are never handed out,
+    /// so a relocated chunk keeps its old copy until the header moves.
+    fn allocate(&self, needed: u32) -> u32 {
+        let mut used: Vec<(u32, u32)> = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| (entry.sector, entry.sector + entry.sectors()))
+            .collect();
+        used.sort_unstable();
+
+        let mut start: u32 = HEADER_SECTORS;
+
+        for (used_start, used_end) in used {
+            if used_start >= start + needed {
+                return start;
+            }
+            start = start.max(used_end);
+        }
+
+        start
+    }
+
+    fn set_entry(&mut self, index: usize, entry: Entry) -> io::Result<()> {
+        let mut bytes: [u8; ENTRY_SIZE] = [0; ENTRY_SIZE];
+        bytes[0..4].copy_from_slice(&entry.sector.to_le_bytes());
+        bytes[4..8].copy_from_slice(&entry.len.to_le_bytes());
+
+        self.file
+            .seek(SeekFrom::Start((index * ENTRY_SIZE) as u64))?;
+        self.file.write_all(&bytes)?;
+        self.file.sync_data()?;
+
+        self.entries[index] = entry;
+
+        Ok(())
+    }
+}
+
+/// Region file kept open by a [`RegionStore`], with the time it was last used.
+struct OpenRegion {
+    file: Arc<Mutex<RegionFile>>,
+    last_use: u64,
+}
+
+/// Keeps the region files under a directory open and routes chunk blobs to them.
+/// At most [`MAX_OPEN_REGIONS`] files stay open by default, closing the least recently used ones first.
+/// Calls block on file I/O, so async code should run them on a blocking thread.
+pub struct RegionStore {
+    root: PathBuf,
+    files: Mutex<HashMap<RegionPosition, OpenRegion>>,
+    clock: AtomicU64,
+    max_open: usize,
+}
+
+impl RegionStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            files: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            max_open: MAX_OPEN_REGIONS,
+        }
+    }
+
+    /// Sets how many region files stay open at once.
+    /// Files still in use are never closed, so more may be open for a moment.
+    pub fn max_open(mut self, max_open: usize) -> Self {
+        self.max_open = max_open.max(1);
+        self
+    }
+
+    /// Returns the number of region files currently open.
+    pub fn open_regions(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    /// Returns the directory region files are stored in.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the file path of the region holding the passed chunk.
+    pub fn region_path(&self, pos: ChunkPosition) -> PathBuf {
+        self.region_file_path(chunk_to_region_pos(pos))
+    }
+
+    /// Returns the blob stored for the passed chunk, if any.
+    pub fn read(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+        if !self.region_path(pos).exists() {
+            return Ok(None);
+        }
+
+        self.region(chunk_to_region_pos(pos))?
+            .lock()
+            .unwrap()
+            .read(pos)
+    }
+
+    /// Stores the blob for the passed chunk.
+    pub fn write(&self, pos: ChunkPosition, data: &[u8]) -> io::Result<()> {
+        self.region(chunk_to_region_pos(pos))?
+            .lock()
+            .unwrap()
+            .write(pos, data)
+    }
+
+    /// Removes the blob for the passed chunk.
+    pub fn delete(&self, pos: ChunkPosition) -> io::Result<()> {
+        if !self.region_path(pos).exists() {
+            return Ok(());
+        }
+
+        self.region(chunk_to_region_pos(pos))?
+            .lock()
+            .unwrap()
+            .delete(pos)
+    }
+
+    /// Returns the path a corrupted chunk is moved to by [`RegionStore::quarantine`].
+    pub fn quarantine_path(&self, pos: ChunkPosition) -> PathBuf {
+        self.root.join(format!("{}_{}.bin.corrupt", pos.x, pos.y))
+    }
+
+    /// Returns the path the region holding the passed chunk is moved to if its header is damaged.
+    pub fn region_quarantine_path(&self, pos: ChunkPosition) -> PathBuf {
+        let region: RegionPosition = chunk_to_region_pos(pos);
+        self.root
+            .join(format!("r.{}.{}.region.corrupt", region.x, region.y))
+    }
+
+    /// Copies the blob for the passed chunk into its own file next to the regions,
+    /// then removes it from its region. Entries pointing outside the file are only removed.
+    /// A region whose header is damaged is moved aside as a whole, as none of its chunks can be reached.
+    pub fn quarantine(&self, pos: ChunkPosition) -> io::Result<()> {
+        if self.region_path(pos).exists() {
+            match self.region(chunk_to_region_pos(pos)) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    return fs::rename(self.region_path(pos), self.region_quarantine_path(pos));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        match self.read(pos) {
+            Ok(Some(data)) => fs::write(self.quarantine_path(pos), data)?,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {}
+            Err(e) => return Err(e),
+        }
+
+        self.delete(pos)
+    }
+
+    /// Returns the positions of all chunks stored in region files under the root directory.
+    pub fn positions(&self) -> io::Result<Vec<ChunkPosition>> {
+        let Some(entries) = not_found_as_none(fs::read_dir(&self.root))? else {
+            return Ok(Vec::new());
+        };
+
+        let mut positions: Vec<ChunkPosition> = Vec::new();
+
+        for entry in entries {
+            let Some(region) = entry?.file_name().to_str().and_then(Self::parse_file_name) else {
+                continue;
+            };
+
+            positions.extend(self.region(region)?.lock().unwrap().positions(region));
+        }
+
+        Ok(positions)
+    }
+
+    fn region_file_path(&self, region: RegionPosition) -> PathBuf {
+        self.root
+            .join(format!("r.{}.{}.region", region.x, region.y))
+    }
+
+    /// Parses a region position back out of a region file name.
+    fn parse_file_name(name: &str) -> Option<RegionPosition> {
+        let (x, y) = name
+            .strip_prefix("r.")?
+            .strip_suffix(".region")?
+            .split_once('.')?;
+        Some(RegionPosition::new(x.parse().ok()?, y.parse().ok()?))
+    }
+
+    fn region(&self, region: RegionPosition) -> io::Result<Arc<Mutex<RegionFile>>> {
+        let mut files = self.files.lock().unwrap();
+        let now: u64 = self.clock.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(open) = files.get_mut(&region) {
+            open.last_use = now;
+            return Ok(Arc::clone(&open.file));
+        }
+
+        fs::create_dir_all(&self.root)?;
+        let file: Arc<Mutex<RegionFile>> =
+            Arc::new(Mutex::new(RegionFile::open(self.region_file_path(region))?));
+
+        while files.len() >= self.max_open {
+            // handles are only cloned under the lock, so a single owner means no call is using the file.
+            // Closing one in use would let a second handle with its own header table open the same file.
+            let Some(idle) = files
+                .iter()
+                .filter(|(_, open)| Arc::strong_count(&open.file) == 1)
+                .min_by_key(|(_, open)| open.last_use)
+                .map(|(&region, _)| region)
+            else {
+                break;
+            };
+
+            files.remove(&idle);
+        }
+
+        files.insert(
+            region,
+            OpenRegion {
+                file: Arc::clone(&file),
+                last_use: now,
+            },
+        );
+
+        Ok(file)
+    }
+}
+
+/// [`ChunkStorage`] backend grouping chunks into region files.
+/// File I/O of the async methods runs on tokio's blocking thread pool.
+pub struct RegionStorage {
+    store: Arc<RegionStore>,
+}
+
+impl RegionStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            store: Arc::new(RegionStore::new(root)),
+        }
+    }
+
+    /// Returns the underlying region files.
+    #[inline]
+    pub fn store(&self) -> &RegionStore {
+        &self.store
+    }
+
+    /// Returns the file path of the world metadata.
+    pub fn metadata_path(&self) -> PathBuf {
+        self.store.root().join(METADATA_FILE)
+    }
+
+    #[cfg(feature = "async-io")]
+    async fn blocking<T, F>(&self, f: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&RegionStore) -> io::Result<T> + Send + 'static,
+    {
+        let store: Arc<RegionStore> = Arc::clone(&self.store);
+        task::spawn_blocking(move || f(&store))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+impl ChunkStorage for RegionStorage {
+    #[cfg(feature = "async-io")]
+    fn read(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        Box::pin(self.blocking(move |store| store.read(pos)))
+    }
+
+    #[cfg(feature = "async-io")]
+    fn write(&self, pos: ChunkPosition, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(self.blocking(move |store| store.write(pos, &data)))
+    }
+
+    #[cfg(feature = "async-io")]
+    fn delete(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(self.blocking(move |store| store.delete(pos)))
+    }
+
+    #[cfg(feature = "async-io")]
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<ChunkPosition>>> {
+        Box::pin(self.blocking(|store| store.positions()))
+    }
+
+    #[cfg(feature = "async-io")]
+    fn read_metadata(&self) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        let path: PathBuf = self.metadata_path();
+        Box::pin(async move { not_found_as_none(tokio::fs::read(path).await) })
+    }
+
+    #[cfg(feature = "async-io")]
+    fn write_metadata(&self, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        let root: PathBuf = self.store.root().to_path_buf();
+        Box::pin(async move {
+            tokio::fs::create_dir_all(&root).await?;
+            write_atomic(&root.join(METADATA_FILE), &data).await
+        })
+    }
+
+    fn path(&self, pos: ChunkPosition) -> Option<PathBuf> {
+        Some(self.store.region_path(pos))
+    }
+
+    #[cfg(feature = "async-io")]
+    fn quarantine(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(self.blocking(move |store| store.quarantine(pos)))
+    }
+
+    fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+        self.store.read(pos)
+    }
+
+    fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()> {
+        self.store.write(pos, &data)
+    }
+
+    fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        self.store.delete(pos)
+    }
+
+    fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>> {
+        self.store.positions()
+    }
+
+    fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>> {
+        not_found_as_none(fs::read(self.metadata_path()))
+    }
+
+    fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()> {
+        fs::create_dir_all(self.store.root())?;
+        write_atomic_blocking(&self.metadata_path(), &data)
+    }
+
+    fn quarantine_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        self.store.quarantine(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        let path: PathBuf = std::env::temp_dir().join(format!("terrain_data_region_{name}.region"));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_region_round_trip() -> io::Result<()> {
+        let path: PathBuf = test_path("round_trip");
+        let pos_1: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos_2: ChunkPosition = ChunkPosition::new(31, 31);
+
+        let mut region: RegionFile = RegionFile::open(&path)?;
+        region.write(pos_1, &[1; 10])?;
+        region.write(pos_2, &[2; 5000])?;
+        drop(region);
+
+        let mut region: RegionFile = RegionFile::open(&path)?;
+        assert_eq!(region.read(pos_1)?, Some(vec![1; 10]));
+        assert_eq!(region.read(pos_2)?, Some(vec![2; 5000]));
+        assert_eq!(region.read(ChunkPosition::new(1, 0))?, None);
+        assert_eq!(region.positions(RegionPosition::ZERO).count(), 2);
+
+        fs::remove_file(&path)
+    }
+
+    #[test]
+    fn test_region_reuses_free_sectors() -> io::Result<()> {
+        let path: PathBuf = test_path("reuse");
+        let pos_1: ChunkPosition = ChunkPosition::new(0, 0);
+        let pos_2: ChunkPosition = ChunkPosition::new(1, 0);
+        let pos_3: ChunkPosition = ChunkPosition::new(2, 0);
+
+        let mut region: RegionFile = RegionFile::open(&path)?;
+        region.write(pos_1, &[1; 100])?;
+        region.write(pos_2, &[2; 100])?;
+        let first_sector: u32 = region.entries[local_index(pos_1)].sector;
+
+        // growing past its sector moves the chunk to the end of the file
+        region.write(pos_1, &[3; 5000])?;
+        assert_ne!(region.entries[local_index(pos_1)].sector, first_sector);
+
+        // the freed sector is handed to the next chunk that fits
+        region.write(pos_3, &[4; 100])?;
+        assert_eq!(region.entries[local_index(pos_3)].sector, first_sector);
+
+        // even a blob that fits its old sectors is written elsewhere, which frees them afterwards
+        let grown_sector: u32 = region.entries[local_index(pos_1)].sector;
+        region.write(pos_1, &[5; 10])?;
+        assert_ne!(region.entries[local_index(pos_1)].sector, grown_sector);
+        region.write(pos_2, &[6; 5000])?;
+        assert_eq!(region.entries[local_index(pos_2)].sector, grown_sector);
+
+        region.delete(pos_2)?;
+        assert!(!region.contains(pos_2));
+        assert_eq!(region.read(pos_1)?, Some(vec![5; 10]));
+        assert_eq!(region.read(pos_3)?, Some(vec![4; 100]));
+
+        fs::remove_file(&path)
+    }
+
+    #[test]
+    fn test_damaged_entries_are_corruption() -> io::Result<()> {
+        let path: PathBuf = test_path("damaged");
+        let pos: ChunkPosition = ChunkPosition::new(3, 0);
+
+        let mut region: RegionFile = RegionFile::open(&path)?;
+        region.write(pos, &[1; 10])?;
+        region.set_entry(
+            local_index(pos),
+            Entry {
+                sector: HEADER_SECTORS,
+                len: u32::MAX,
+            },
+        )?;
+
+        let error: io::Error = region.read(pos).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            error
+                .into_inner()
+                .unwrap()
+                .downcast::<CorruptionError>()
+                .as_deref(),
+            Ok(CorruptionError::RegionEntry { len: u32::MAX, .. })
+        ));
+
+        fs::remove_file(&path)
+    }
+
+    #[test]
+    fn test_negative_positions_map_to_regions() {
+        assert_eq!(
+            chunk_to_region_pos(ChunkPosition::new(-1, 0)),
+            RegionPosition::new(-1, 0)
+        );
+        assert_eq!(local_index(ChunkPosition::new(-1, -32)), 31);
+    }
+
+    #[test]
+    fn test_truncated_header_is_corruption() -> io::Result<()> {
+        let path: PathBuf = test_path("truncated_header");
+        fs::write(&path, [0; 100])?;
+
+        let error: io::Error = RegionFile::open(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            error
+                .into_inner()
+                .unwrap()
+                .downcast::<CorruptionError>()
+                .as_deref(),
+            Ok(CorruptionError::RegionHeader { len: 100 })
+        ));
+
+        fs::remove_file(&path)
+    }
+
+    #[test]
+    fn test_damaged_region_is_quarantined_whole() -> io::Result<()> {
+        let root: PathBuf = std::env::temp_dir().join("terrain_data_region_quarantine_header");
+        let _ = fs::remove_dir_all(&root);
+        let pos: ChunkPosition = ChunkPosition::new(2, 2);
+
+        let store: RegionStore = RegionStore::new(&root);
+        store.write(pos, &[1; 10])?;
+        drop(store);
+
+        let store: RegionStore = RegionStore::new(&root);
+        File::options()
+            .write(true)
+            .open(store.region_path(pos))?
+            .set_len(10)?;
+        assert_eq!(
+            store.read(pos).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        store.quarantine(pos)?;
+        assert!(store.region_quarantine_path(pos).exists());
+        assert_eq!(store.read(pos)?, None);
+
+        store.write(pos, &[2; 10])?;
+        assert_eq!(store.read(pos)?, Some(vec![2; 10]));
+
+        fs::remove_dir_all(&root)
+    }
+
+    #[test]
+    fn test_open_regions_are_bounded() -> io::Result<()> {
+        let root: PathBuf = std::env::temp_dir().join("terrain_data_region_bounded");
+        let _ = fs::remove_dir_all(&root);
+
+        let store: RegionStore = RegionStore::new(&root).max_open(2);
+
+        for x in 0..5 {
+            store.write(ChunkPosition::new(x * REGION_WIDTH, 0), &[x as u8 + 1; 10])?;
+        }
+        assert_eq!(store.open_regions(), 2);
+
+        // closed regions are opened again on demand
+        assert_eq!(store.read(ChunkPosition::ZERO)?, Some(vec![1; 10]));
+        assert_eq!(store.open_regions(), 2);
+
+        // a region in use is never closed
+        let held: Arc<Mutex<RegionFile>> = store.region(RegionPosition::new(1, 0))?;
+        store.read(ChunkPosition::new(2 * REGION_WIDTH, 0))?;
+        store.read(ChunkPosition::new(3 * REGION_WIDTH, 0))?;
+        assert!(Arc::ptr_eq(
+            &held,
+            &store.region(RegionPosition::new(1, 0))?
+        ));
+        drop(held);
+
+        fs::remove_dir_all(&root)
+    }
+}
@@ -0,0 +1,457 @@
+use crate::core::{ChunkPosition, METADATA_FILE};
+use dashmap::DashMap;
+use std::{
+    fs,
+    future::Future,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    process,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+#[cfg(feature = "async-io")]
+use tokio::io::AsyncWriteExt;
+
+/// Boxed future returned by [`ChunkStorage`] methods so the trait stays object safe.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Persists encoded chunk blobs keyed by chunk position.
+///
+/// A world holds its storage as a trait object,
+/// so custom backends such as databases can be plugged in through `WorldBuilder::storage`.
+///
+/// Backends implement the `*_blocking` methods, which back the blocking world API.
+/// The async methods back the async world API and only exist with the `async-io` feature.
+/// They run the blocking methods on the calling task by default,
+/// so backends doing slow IO should override them to keep the runtime's threads free.
+pub trait ChunkStorage: Send + Sync {
+    /// Returns the blob stored for the passed chunk, or none if it was never written.
+    fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>>;
+
+    /// Stores the blob for the passed chunk, replacing any previous one.
+    fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()>;
+
+    /// Removes the blob for the passed chunk. Removing a missing blob is not an error.
+    fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()>;
+
+    /// Returns the positions of all stored chunks.
+    fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>>;
+
+    /// Returns the stored world metadata blob, or none if it was never written.
+    fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>>;
+
+    /// Stores the world metadata blob.
+    fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()>;
+
+    /// Returns the file a chunk is stored in, if the backend is file based.
+    fn path(&self, _pos: ChunkPosition) -> Option<PathBuf> {
+        None
+    }
+
+    /// Moves the blob for the passed chunk out of the way after it was found to be corrupted.
+    /// Backends able to keep it for inspection should do so, the default deletes it.
+    fn quarantine_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        self.delete_blocking(pos)
+    }
+
+    /// Async version of [`ChunkStorage::read_blocking`].
+    #[cfg(feature = "async-io")]
+    fn read(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        Box::pin(async move { self.read_blocking(pos) })
+    }
+
+    /// Async version of [`ChunkStorage::write_blocking`].
+    #[cfg(feature = "async-io")]
+    fn write(&self, pos: ChunkPosition, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move { self.write_blocking(pos, data) })
+    }
+
+    /// Async version of [`ChunkStorage::delete_blocking`].
+    #[cfg(feature = "async-io")]
+    fn delete(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move { self.delete_blocking(pos) })
+    }
+
+    /// Async version of [`ChunkStorage::list_blocking`].
+    #[cfg(feature = "async-io")]
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<ChunkPosition>>> {
+        Box::pin(async move { self.list_blocking() })
+    }
+
+    /// Async version of [`ChunkStorage::read_metadata_blocking`].
+    #[cfg(feature = "async-io")]
+    fn read_metadata(&self) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        Box::pin(async move { self.read_metadata_blocking() })
+    }
+
+    /// Async version of [`ChunkStorage::write_metadata_blocking`].
+    #[cfg(feature = "async-io")]
+    fn write_metadata(&self, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move { self.write_metadata_blocking(data) })
+    }
+
+    /// Async version of [`ChunkStorage::quarantine_blocking`].
+    #[cfg(feature = "async-io")]
+    fn quarantine(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move { self.quarantine_blocking(pos) })
+    }
+}
+
+/// Lets a single backend be shared between worlds or inspected while a world uses it.
+impl<S: ChunkStorage + ?Sized> ChunkStorage for Arc<S> {
+    #[cfg(feature = "async-io")]
+    fn read(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        (**self).read(pos)
+    }
+
+    #[cfg(feature = "async-io")]
+    fn write(&self, pos: ChunkPosition, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        (**self).write(pos, data)
+    }
+
+    #[cfg(feature = "async-io")]
+    fn delete(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        (**self).delete(pos)
+    }
+
+    #[cfg(feature = "async-io")]
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<ChunkPosition>>> {
+        (**self).list()
+    }
+
+    #[cfg(feature = "async-io")]
+    fn read_metadata(&self) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        (**self).read_metadata()
+    }
+
+    #[cfg(feature = "async-io")]
+    fn write_metadata(&self, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        (**self).write_metadata(data)
+    }
+
+    fn path(&self, pos: ChunkPosition) -> Option<PathBuf> {
+        (**self).path(pos)
+    }
+
+    #[cfg(feature = "async-io")]
+    fn quarantine(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        (**self).quarantine(pos)
+    }
+
+    fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+        (**self).read_blocking(pos)
+    }
+
+    fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()> {
+        (**self).write_blocking(pos, data)
+    }
+
+    fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        (**self).delete_blocking(pos)
+    }
+
+    fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>> {
+        (**self).list_blocking()
+    }
+
+    fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>> {
+        (**self).read_metadata_blocking()
+    }
+
+    fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()> {
+        (**self).write_metadata_blocking(data)
+    }
+
+    fn quarantine_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        (**self).quarantine_blocking(pos)
+    }
+}
+
+/// Maps a missing file to none so callers can tell it apart from real failures.
+pub(crate) fn not_found_as_none<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns a temporary sibling path unique to this write,
+/// so concurrent writes of the same file never share a temporary file.
+fn tmp_path(path: &Path) -> PathBuf {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    let id: u64 = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut tmp_path: PathBuf = path.to_path_buf();
+    tmp_path
+        .as_mut_os_string()
+        .push(format!(".{}.{id}.tmp", process::id()));
+    tmp_path
+}
+
+/// Replaces the file at the passed path without ever leaving it partially written.
+/// The data is written and synced to a temporary sibling file which is then renamed over the target.
+#[cfg(feature = "async-io")]
+pub(crate) async fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    use tokio::fs;
+
+    let tmp_path: PathBuf = tmp_path(path);
+
+    let result: io::Result<()> = async {
+        let mut file: fs::File = fs::File::create(&tmp_path).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        fs::rename(&tmp_path, path).await
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path).await;
+        return result;
+    }
+
+    // persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        fs::File::open(parent).await?.sync_all().await?;
+    }
+
+    Ok(())
+}
+
+/// Blocking version of [`write_atomic`].
+pub(crate) fn write_atomic_blocking(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp_path: PathBuf = tmp_path(path);
+
+    let result: io::Result<()> = (|| {
+        let mut file: fs::File = fs::File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        fs::File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+// -- FileStorage --
+
+/// Stores every chunk in its own `"{x}_{y}.bin"` file under a root directory.
+/// Files are replaced atomically, so a failed or interrupted write keeps the previous version.
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the directory chunk files are stored in.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the file path the passed chunk is stored in.
+    pub fn chunk_path(&self, pos: ChunkPosition) -> PathBuf {
+        self.root.join(format!("{}_{}.bin", pos.x, pos.y))
+    }
+
+    /// Returns the file path of the world metadata.
+    pub fn metadata_path(&self) -> PathBuf {
+        self.root.join(METADATA_FILE)
+    }
+
+    /// Returns the path a corrupted chunk file is moved to by [`ChunkStorage::quarantine_blocking`].
+    pub fn quarantine_path(&self, pos: ChunkPosition) -> PathBuf {
+        self.root.join(format!("{}_{}.bin.corrupt", pos.x, pos.y))
+    }
+
+    /// Parses a chunk position back out of a chunk file name.
+    fn parse_file_name(name: &str) -> Option<ChunkPosition> {
+        let (x, y) = name.strip_suffix(".bin")?.split_once('_')?;
+        Some(ChunkPosition::new(x.parse().ok()?, y.parse().ok()?))
+    }
+}
+
+impl ChunkStorage for FileStorage {
+    #[cfg(feature = "async-io")]
+    fn read(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        Box::pin(async move { not_found_as_none(tokio::fs::read(self.chunk_path(pos)).await) })
+    }
+
+    #[cfg(feature = "async-io")]
+    fn write(&self, pos: ChunkPosition, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            tokio::fs::create_dir_all(&self.root).await?;
+            write_atomic(&self.chunk_path(pos), &data).await
+        })
+    }
+
+    #[cfg(feature = "async-io")]
+    fn delete(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            not_found_as_none(tokio::fs::remove_file(self.chunk_path(pos)).await)?;
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "async-io")]
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<ChunkPosition>>> {
+        Box::pin(async move {
+            let Some(mut entries) = not_found_as_none(tokio::fs::read_dir(&self.root).await)?
+            else {
+                return Ok(Vec::new());
+            };
+
+            let mut positions: Vec<ChunkPosition> = Vec::new();
+
+            while let Some(entry) = entries.next_entry().await? {
+                if let Some(pos) = entry.file_name().to_str().and_then(Self::parse_file_name) {
+                    positions.push(pos);
+                }
+            }
+
+            Ok(positions)
+        })
+    }
+
+    #[cfg(feature = "async-io")]
+    fn read_metadata(&self) -> BoxFuture<'_, io::Result<Option<Vec<u8>>>> {
+        Box::pin(async move { not_found_as_none(tokio::fs::read(self.metadata_path()).await) })
+    }
+
+    #[cfg(feature = "async-io")]
+    fn write_metadata(&self, data: Vec<u8>) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            tokio::fs::create_dir_all(&self.root).await?;
+            write_atomic(&self.metadata_path(), &data).await
+        })
+    }
+
+    fn path(&self, pos: ChunkPosition) -> Option<PathBuf> {
+        Some(self.chunk_path(pos))
+    }
+
+    #[cfg(feature = "async-io")]
+    fn quarantine(&self, pos: ChunkPosition) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async move {
+            not_found_as_none(
+                tokio::fs::rename(self.chunk_path(pos), self.quarantine_path(pos)).await,
+            )?;
+            Ok(())
+        })
+    }
+
+    fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+        not_found_as_none(fs::read(self.chunk_path(pos)))
+    }
+
+    fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+        write_atomic_blocking(&self.chunk_path(pos), &data)
+    }
+
+    fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        not_found_as_none(fs::remove_file(self.chunk_path(pos)))?;
+        Ok(())
+    }
+
+    fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>> {
+        let Some(entries) = not_found_as_none(fs::read_dir(&self.root))? else {
+            return Ok(Vec::new());
+        };
+
+        let mut positions: Vec<ChunkPosition> = Vec::new();
+
+        for entry in entries {
+            if let Some(pos) = entry?.file_name().to_str().and_then(Self::parse_file_name) {
+                positions.push(pos);
+            }
+        }
+
+        Ok(positions)
+    }
+
+    fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>> {
+        not_found_as_none(fs::read(self.metadata_path()))
+    }
+
+    fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+        write_atomic_blocking(&self.metadata_path(), &data)
+    }
+
+    fn quarantine_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        not_found_as_none(fs::rename(self.chunk_path(pos), self.quarantine_path(pos)))?;
+        Ok(())
+    }
+}
+
+// -- MemoryStorage --
+
+/// Keeps chunk blobs in memory. Useful for tests and throwaway worlds.
+#[derive(Default)]
+pub struct MemoryStorage {
+    chunks: DashMap<ChunkPosition, Vec<u8>>,
+    metadata: Mutex<Option<Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of stored chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if no chunks are stored.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+impl ChunkStorage for MemoryStorage {
+    fn read_blocking(&self, pos: ChunkPosition) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.chunks.get(&pos).map(|data| data.clone()))
+    }
+
+    fn write_blocking(&self, pos: ChunkPosition, data: Vec<u8>) -> io::Result<()> {
+        self.chunks.insert(pos, data);
+        Ok(())
+    }
+
+    fn delete_blocking(&self, pos: ChunkPosition) -> io::Result<()> {
+        self.chunks.remove(&pos);
+        Ok(())
+    }
+
+    fn list_blocking(&self) -> io::Result<Vec<ChunkPosition>> {
+        Ok(self.chunks.iter().map(|entry| *entry.key()).collect())
+    }
+
+    fn read_metadata_blocking(&self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.metadata.lock().unwrap().clone())
+    }
+
+    fn write_metadata_blocking(&self, data: Vec<u8>) -> io::Result<()> {
+        *self.metadata.lock().unwrap() = Some(data);
+        Ok(())
+    }
+}
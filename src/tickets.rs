@@ -0,0 +1,361 @@
+use crate::{
+    core::ChunkPosition,
+    error::{AccessError, ChunkAccessError, ChunkStoreError},
+    parallel::run_limited,
+    storage::BoxFuture,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Loads and unloads chunks on behalf of a [`ChunkTicketManager`].
+/// Implemented by every world generated by `world!`.
+pub trait ChunkLoader: Send + Sync + 'static {
+    /// Makes the chunk at the passed position available, loading or creating it as needed.
+    /// Returns true if this call loaded or created the chunk, false if it was already loaded.
+    fn load(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<bool, ChunkStoreError>>;
+
+    /// Saves and removes the chunk at the passed position.
+    fn unload(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<(), ChunkStoreError>>;
+}
+
+/// Area around a viewer that is kept loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViewShape {
+    /// Every chunk at most `radius` chunks away along both axes.
+    #[default]
+    Square,
+    /// Every chunk whose distance to the center is at most `radius` chunks.
+    Circle,
+}
+
+impl ViewShape {
+    /// Returns true if the passed offset from a viewer lies within its area.
+    pub fn contains(&self, offset: ChunkPosition, radius: u32) -> bool {
+        let radius: i64 = radius as i64;
+        let (x, y) = (offset.x as i64, offset.y as i64);
+
+        match self {
+            Self::Square => x.abs() <= radius && y.abs() <= radius,
+            Self::Circle => x * x + y * y <= radius * radius,
+        }
+    }
+}
+
+/// Handle identifying a viewer registered with a [`ChunkTicketManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ViewerId(u64);
+
+#[derive(Debug, Clone, Copy)]
+struct Viewer {
+    center: ChunkPosition,
+    radius: u32,
+    shape: ViewShape,
+}
+
+/// Outcome of a single [`ChunkTicketManager::tick`].
+#[derive(Debug, Default)]
+pub struct TickReport {
+    /// Chunks loaded or created by the manager, closest to a viewer first.
+    pub loaded: Vec<ChunkPosition>,
+    /// Chunks saved and removed after leaving every viewer's range.
+    pub unloaded: Vec<ChunkPosition>,
+    /// Chunks that failed to load or unload. They are retried on the next tick.
+    pub errors: Vec<(ChunkPosition, ChunkStoreError)>,
+}
+
+/// Keeps the chunks around registered viewers loaded.
+///
+/// Every tick loads missing chunks in range of any viewer, closest first,
+/// and unloads chunks it loaded once they stayed out of every viewer's range for the grace period.
+/// Chunks loaded by other means are never unloaded by the manager.
+pub struct ChunkTicketManager<W> {
+    world: Arc<W>,
+    viewers: Mutex<HashMap<ViewerId, Viewer>>,
+    next_id: AtomicU64,
+    /// Chunks loaded by the manager, with the time they left every viewer's range.
+    tickets: Mutex<HashMap<ChunkPosition, Option<Instant>>>,
+    grace_period: Duration,
+    max_concurrent: usize,
+}
+
+impl<W: ChunkLoader> ChunkTicketManager<W> {
+    /// Creates a manager with a grace period of 5 seconds, running up to 16 loads or unloads at once.
+    pub fn new(world: Arc<W>) -> Self {
+        Self {
+            world,
+            viewers: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            tickets: Mutex::new(HashMap::new()),
+            grace_period: Duration::from_secs(5),
+            max_concurrent: 16,
+        }
+    }
+
+    /// Sets how long a chunk stays loaded after leaving every viewer's range.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets how many loads or unloads run at once during a tick.
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self
+    }
+
+    /// Returns the world chunks are loaded into.
+    #[inline]
+    pub fn world(&self) -> &Arc<W> {
+        &self.world
+    }
+
+    /// Registers a viewer keeping the area around the passed chunk loaded.
+    pub fn add_viewer(&self, center: ChunkPosition, radius: u32, shape: ViewShape) -> ViewerId {
+        let id: ViewerId = ViewerId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let viewer: Viewer = Viewer {
+            center,
+            radius,
+            shape,
+        };
+        self.viewers.lock().unwrap().insert(id, viewer);
+        id
+    }
+
+    /// Moves a viewer to the passed chunk. Returns false if the viewer was removed.
+    pub fn move_viewer(&self, id: ViewerId, center: ChunkPosition) -> bool {
+        self.update_viewer(id, |viewer| viewer.center = center)
+    }
+
+    /// Changes the radius of a viewer. Returns false if the viewer was removed.
+    pub fn set_viewer_radius(&self, id: ViewerId, radius: u32) -> bool {
+        self.update_viewer(id, |viewer| viewer.radius = radius)
+    }
+
+    /// Removes a viewer. Its chunks are unloaded once the grace period passes.
+    pub fn remove_viewer(&self, id: ViewerId) -> bool {
+        self.viewers.lock().unwrap().remove(&id).is_some()
+    }
+
+    /// Returns true if the manager loaded the chunk at the passed position and still tracks it.
+    pub fn has_ticket(&self, pos: ChunkPosition) -> bool {
+        self.tickets.lock().unwrap().contains_key(&pos)
+    }
+
+    /// Returns every chunk in range of a viewer, ordered by distance to the closest viewer.
+    pub fn desired_chunks(&self) -> Vec<ChunkPosition> {
+        let viewers: Vec<Viewer> = self.viewers.lock().unwrap().values().copied().collect();
+        let mut distances: HashMap<ChunkPosition, i64> = HashMap::new();
+
+        for viewer in &viewers {
+            let radius: i32 = viewer.radius as i32;
+
+            for x in -radius..=radius {
+                for y in -radius..=radius {
+                    let offset: ChunkPosition = ChunkPosition::new(x, y);
+
+                    if !viewer.shape.contains(offset, viewer.radius) {
+                        continue;
+                    }
+
+                    let distance: i64 = (x as i64).pow(2) + (y as i64).pow(2);
+                    distances
+                        .entry(viewer.center + offset)
+                        .and_modify(|closest| *closest = (*closest).min(distance))
+                        .or_insert(distance);
+                }
+            }
+        }
+
+        let mut desired: Vec<(ChunkPosition, i64)> = distances.into_iter().collect();
+        // ties broken by position so the order is stable
+        desired.sort_unstable_by_key(|&(pos, distance)| (distance, pos.x, pos.y));
+        desired.into_iter().map(|(pos, _)| pos).collect()
+    }
+
+    /// Loads chunks that came into range and unloads chunks whose grace period ran out.
+    pub async fn tick(&self) -> TickReport {
+        let now: Instant = Instant::now();
+        let desired: Vec<ChunkPosition> = self.desired_chunks();
+        let in_range: HashSet<ChunkPosition> = desired.iter().copied().collect();
+
+        let (to_load, to_unload) = {
+            let mut tickets = self.tickets.lock().unwrap();
+
+            for (pos, left_at) in tickets.iter_mut() {
+                if in_range.contains(pos) {
+                    *left_at = None;
+                } else {
+                    left_at.get_or_insert(now);
+                }
+            }
+
+            let to_unload: Vec<ChunkPosition> = tickets
+                .iter()
+                .filter(|(_, left_at)| left_at.is_some_and(|t| now - t >= self.grace_period))
+                .map(|(&pos, _)| pos)
+                .collect();
+
+            // ticketed chunks are loaded again too, as they may have been evicted or unloaded since
+            (desired, to_unload)
+        };
+
+        let mut report: TickReport = TickReport::default();
+
+        // unload first so memory is freed before new chunks arrive
+        for (pos, result) in run_limited(
+            &self.world,
+            self.max_concurrent,
+            to_unload,
+            |world, pos| async move { world.unload(pos).await },
+        )
+        .await
+        {
+            match result {
+                // already unloaded by someone else
+                Ok(())
+                | Err(ChunkStoreError::Access(AccessError::ChunkAccess(
+                    ChunkAccessError::ChunkUnloaded(_),
+                ))) => {
+                    self.tickets.lock().unwrap().remove(&pos);
+                    report.unloaded.push(pos);
+                }
+                Err(e) => report.errors.push((pos, e)),
+            }
+        }
+
+        for (pos, result) in run_limited(
+            &self.world,
+            self.max_concurrent,
+            to_load,
+            |world, pos| async move { world.load(pos).await },
+        )
+        .await
+        {
+            match result {
+                Ok(true) => {
+                    self.tickets.lock().unwrap().insert(pos, None);
+                    report.loaded.push(pos);
+                }
+                // loaded by other means, which stay responsible for unloading it
+                Ok(false) => {}
+                Err(e) => report.errors.push((pos, e)),
+            }
+        }
+
+        report
+    }
+
+    fn update_viewer(&self, id: ViewerId, update: impl FnOnce(&mut Viewer)) -> bool {
+        self.viewers
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .map(update)
+            .is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records loads and unloads instead of touching a world.
+    #[derive(Default)]
+    struct RecordingLoader {
+        loaded: Mutex<HashSet<ChunkPosition>>,
+        loads: Mutex<Vec<ChunkPosition>>,
+        unloads: Mutex<Vec<ChunkPosition>>,
+    }
+
+    impl ChunkLoader for RecordingLoader {
+        fn load(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<bool, ChunkStoreError>> {
+            Box::pin(async move {
+                if !self.loaded.lock().unwrap().insert(pos) {
+                    return Ok(false);
+                }
+
+                self.loads.lock().unwrap().push(pos);
+                Ok(true)
+            })
+        }
+
+        fn unload(&self, pos: ChunkPosition) -> BoxFuture<'_, Result<(), ChunkStoreError>> {
+            Box::pin(async move {
+                self.loaded.lock().unwrap().remove(&pos);
+                self.unloads.lock().unwrap().push(pos);
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn test_view_shapes() {
+        assert!(ViewShape::Square.contains(ChunkPosition::new(2, -2), 2));
+        assert!(!ViewShape::Circle.contains(ChunkPosition::new(2, -2), 2));
+        assert!(ViewShape::Circle.contains(ChunkPosition::new(0, -2), 2));
+        assert!(!ViewShape::Square.contains(ChunkPosition::new(3, 0), 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_loads_closest_first_and_unloads_after_grace_period() {
+        let loader: Arc<RecordingLoader> = Arc::new(RecordingLoader::default());
+        let manager: ChunkTicketManager<RecordingLoader> =
+            ChunkTicketManager::new(Arc::clone(&loader))
+                .grace_period(Duration::from_secs(2))
+                .max_concurrent(1);
+
+        let viewer: ViewerId = manager.add_viewer(ChunkPosition::ZERO, 1, ViewShape::Circle);
+        let report: TickReport = manager.tick().await;
+
+        assert_eq!(report.loaded[0], ChunkPosition::ZERO);
+        assert_eq!(report.loaded.len(), 5);
+        assert_eq!(*loader.loads.lock().unwrap(), report.loaded);
+
+        // nothing changes while the viewer stays put
+        assert!(manager.tick().await.loaded.is_empty());
+
+        manager.move_viewer(viewer, ChunkPosition::new(1, 0));
+        let report: TickReport = manager.tick().await;
+        assert_eq!(
+            report.loaded,
+            vec![
+                ChunkPosition::new(1, -1),
+                ChunkPosition::new(1, 1),
+                ChunkPosition::new(2, 0)
+            ]
+        );
+        assert!(report.unloaded.is_empty());
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(manager.tick().await.unloaded.is_empty());
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let mut unloaded: Vec<ChunkPosition> = manager.tick().await.unloaded;
+        unloaded.sort_by_key(|pos| (pos.x, pos.y));
+        assert_eq!(
+            unloaded,
+            vec![
+                ChunkPosition::new(-1, 0),
+                ChunkPosition::new(0, -1),
+                ChunkPosition::new(0, 1)
+            ]
+        );
+        assert!(!manager.has_ticket(ChunkPosition::new(-1, 0)));
+        assert!(manager.has_ticket(ChunkPosition::ZERO));
+
+        // returning within the grace period keeps the chunk
+        assert!(manager.remove_viewer(viewer));
+        manager.tick().await;
+        manager.add_viewer(ChunkPosition::new(1, 0), 1, ViewShape::Circle);
+        tokio::time::advance(Duration::from_secs(5)).await;
+        let report: TickReport = manager.tick().await;
+        assert!(report.loaded.is_empty() && report.unloaded.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Ident, parse_macro_input};
+
+/// Derives `terrain_data::core::FieldType` for a fieldless enum with non-negative discriminants.
+///
+/// The field needs as many bits as the largest discriminant. Raw values matching no variant
+/// read back as the variant marked `#[field_type(fallback)]`, or as a decode error without one.
+#[proc_macro_derive(FieldType, attributes(field_type))]
+pub fn derive_field_type(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "FieldType can only be derived for enums",
+        ));
+    };
+
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "FieldType cannot be derived for enums without variants",
+        ));
+    }
+
+    let mut variants: Vec<&Ident> = Vec::with_capacity(data.variants.len());
+    let mut fallback: Option<&Ident> = None;
+
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "FieldType variants cannot have fields",
+            ));
+        }
+
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("field_type"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("fallback") {
+                    return Err(meta.error("expected `fallback`"));
+                }
+                if fallback.is_some() {
+                    return Err(meta.error("only one variant can be the fallback"));
+                }
+
+                fallback = Some(&variant.ident);
+                Ok(())
+            })?;
+        }
+
+        variants.push(&variant.ident);
+    }
+
+    let name: &Ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let unknown: TokenStream2 = match fallback {
+        Some(variant) => quote!(Some(Self::#variant)),
+        None => quote!(None),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::terrain_data::core::FieldType for #name #ty_generics #where_clause {
+            const BITS: u8 = {
+                let discriminants: &[i128] = &[#(Self::#variants as i128),*];
+                let mut max: u64 = 0;
+                let mut i: usize = 0;
+
+                while i < discriminants.len() {
+                    assert!(discriminants[i] >= 0, "FieldType discriminants cannot be negative.");
+                    if discriminants[i] as u64 > max {
+                        max = discriminants[i] as u64;
+                    }
+                    i += 1;
+                }
+
+                // a field needs at least one bit
+                if max == 0 { 1 } else { (u64::BITS - max.leading_zeros()) as u8 }
+            };
+
+            #[inline(always)]
+            fn from_u64(v: u64) -> Option<Self> {
+                #(
+                    if v == Self::#variants as u64 {
+                        return Some(Self::#variants);
+                    }
+                )*
+                #unknown
+            }
+            #[inline(always)]
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+        }
+    })
+}
@@ -0,0 +1,7 @@
+#![cfg(feature = "derive")]
+
+#[test]
+fn test_compile_errors() {
+    let cases: trybuild::TestCases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,12 @@
+use terrain_data::prelude::*;
+
+#[derive(FieldType)]
+enum Kind {
+    #[field_type(fallback)]
+    Unknown,
+    #[field_type(fallback)]
+    Air,
+    Stone,
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use terrain_data::prelude::*;
+
+#[derive(Clone, Copy, FieldType)]
+enum Kind {
+    Below = -1,
+    Air,
+    Stone,
+}
+
+world! {
+    chunk_width: 4,
+    chunk_height: 4,
+    subchunk_depth: 4,
+    num_subchunks: 2,
+    Kind r#as kind: crate::Kind = 2,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use terrain_data::prelude::*;
+
+#[derive(Clone, Copy)]
+struct Light(u8);
+
+impl FieldType for Light {
+    const BITS: u8 = 4;
+
+    fn from_u64(v: u64) -> Option<Self> {
+        Some(Self(v as u8))
+    }
+
+    fn to_u64(self) -> u64 {
+        self.0 as u64
+    }
+}
+
+world! {
+    chunk_width: 4,
+    chunk_height: 4,
+    subchunk_depth: 4,
+    num_subchunks: 2,
+    #[default = crate::Light(15)]
+    SkyLight r#as sky_light: crate::Light = 4,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use terrain_data::prelude::*;
+
+#[derive(FieldType)]
+struct Kind {
+    raw: u8,
+}
+
+fn main() {}
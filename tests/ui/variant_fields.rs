@@ -0,0 +1,10 @@
+use terrain_data::prelude::*;
+
+#[derive(FieldType)]
+enum Kind {
+    Air,
+    Stone(u8),
+    Sand { grain: u8 },
+}
+
+fn main() {}